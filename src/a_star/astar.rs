@@ -0,0 +1,74 @@
+use std::collections::{HashMap, HashSet};
+use super::utils::{
+    structs::{Node, Point, Flags, Solution},
+    cost::Cost,
+    open_list::OpenList,
+    limits::Budget,
+    stats::SearchStats,
+    helpers
+};
+
+/// Single-threaded A*, the baseline the parallel searches are measured
+/// against. Honours the search mode, successor generation and limits, but
+/// not anytime reporting or the thread count.
+/// Its `SearchStats` are the baseline for their search overhead.
+pub fn setup<C: Cost>(start_point: Point, end_point: Point, flags: Flags) -> Solution<C> {
+    let Flags { heur, graph, mode, limits, successors, tie_break, .. } = flags;
+    let budget = Budget::new(limits);
+    let mut open = OpenList::new(mode, tie_break);
+    // Best g found so far for every generated cell, and the parent it came from.
+    let mut best: HashMap<Point, (C, Point)> = HashMap::new();
+    let mut closed: HashSet<Point> = HashSet::new();
+    let mut stats = SearchStats::default();
+
+    let mut start = Node::at(start_point);
+    let end = Node::at(end_point);
+    start.h = helpers::heuristic(start, end, &*heur);
+    start.f = mode.f(start.g, start.h);
+
+    best.insert(start.position, (C::ZERO, Point::default()));
+    open.push(start);
+
+    while let Some(node) = open.pop() {
+        if budget.exceeded() {
+            break;
+        }
+
+        budget.expanded();
+        stats.expansions += 1;
+        closed.insert(node.position);
+
+        if node == end {
+            let path = helpers::trace_path(node.position, graph.len() * graph.len(),
+                                           |point| best.get(&point).map(|(_, parent)| *parent));
+
+            return Solution::new(node.g, helpers::fill_path(path), budget.reason()).with_stats(vec![stats]);
+        }
+
+        for (position, cost) in successors.expand(&graph, &node, end.position) {
+            let g = node.g + cost;
+            stats.generated += 1;
+
+            if best.get(&position).is_some_and(|(known, _)| *known <= g) {
+                stats.duplicates += 1;
+                continue;
+            }
+
+            // Only an inconsistent heuristic finds a cheaper path to an expanded node.
+            if closed.remove(&position) {
+                stats.reopened += 1;
+            }
+
+            let mut n_prime = Node::new(position.x, position.y, C::ZERO, g, C::ZERO, node.position);
+            n_prime.h = helpers::heuristic(n_prime, end, &*heur);
+            stats.evaluations += 1;
+            n_prime.f = mode.f(n_prime.g, n_prime.h);
+
+            best.insert(position, (g, node.position));
+            budget.stored();
+            open.push(n_prime);
+        }
+    }
+
+    Solution::new(C::INFINITY, Vec::new(), budget.reason()).with_stats(vec![stats])
+}
@@ -0,0 +1,279 @@
+use ::atomic::Atomic;
+use std::{
+    thread,
+    mem::drop,
+    cmp::{self, Ordering as CmpOrdering},
+    collections::{HashMap, BinaryHeap},
+    sync::{Arc, Mutex},
+    sync::atomic::{AtomicBool, Ordering}
+};
+use super::utils::{
+    structs::{Incumbent, Node, Point, Flags, Solution},
+    cost::Cost,
+    limits::Budget,
+    pool::Workers,
+    stats::SearchStats,
+    helpers
+};
+
+/// Open list entry ordered by MM priority, max(f, 2g).
+#[derive(Copy, Clone)]
+struct Entry<C: Cost> {
+    priority: C,
+    node: Node<C>,
+}
+
+impl<C: Cost> Entry<C> {
+    fn new(node: Node<C>) -> Entry<C> {
+        Entry { priority: cmp::max(node.f, node.g + node.g), node }
+    }
+}
+
+impl<C: Cost> Ord for Entry<C> {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        other.priority.cmp(&self.priority)
+    }
+}
+
+impl<C: Cost> PartialOrd for Entry<C> {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<C: Cost> Eq for Entry<C> {}
+
+impl<C: Cost> PartialEq for Entry<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+/// One half of the search, grown either from the start or from the goal.
+struct Frontier<C: Cost> {
+    open: BinaryHeap<Entry<C>>,
+    // Best node seen per point, and whether it has been expanded with that g.
+    seen: HashMap<Point, (Node<C>, bool)>,
+    // Priorities of nodes currently being expanded by some thread.
+    expanding: Vec<C>,
+}
+
+impl<C: Cost> Frontier<C> {
+    fn new(root: Node<C>) -> Frontier<C> {
+        let mut open = BinaryHeap::new();
+        let mut seen = HashMap::new();
+
+        open.push(Entry::new(root));
+        seen.insert(root.position, (root, false));
+
+        Frontier { open, seen, expanding: Vec::new() }
+    }
+
+    /// Drops superseded entries from the top of the heap.
+    fn prune(&mut self) {
+        while let Some(entry) = self.open.peek() {
+            match self.seen.get(&entry.node.position) {
+                Some((best, closed)) if best.g < entry.node.g || (*closed && best.g == entry.node.g) => {
+                    self.open.pop();
+                },
+                _ => break,
+            }
+        }
+    }
+
+    /// Lowest priority that can still be expanded, counting nodes in flight.
+    fn min_priority(&mut self) -> C {
+        self.prune();
+
+        let open_min = self.open.peek().map_or(C::INFINITY, |entry| entry.priority);
+        let flight_min = self.expanding.iter().copied().min().unwrap_or(C::INFINITY);
+
+        cmp::min(open_min, flight_min)
+    }
+}
+
+/// MM stopping rule: every optimal path must pass through a node whose
+/// priority is at most C*, so once the incumbent is no larger than the
+/// smallest priority left in either frontier it is optimal. Both locks are
+/// taken, forward first, so the snapshot is consistent.
+fn should_stop<C: Cost>(frontiers: &[Mutex<Frontier<C>>; 2], incumbent: &Atomic<Incumbent<C>>) -> bool {
+    let mut forward = frontiers[0].lock().unwrap();
+    let mut backward = frontiers[1].lock().unwrap();
+
+    if !forward.expanding.is_empty() || !backward.expanding.is_empty() {
+        return false;
+    }
+
+    let lower_bound = cmp::min(forward.min_priority(), backward.min_priority());
+
+    incumbent.load(Ordering::SeqCst).cost <= lower_bound
+}
+
+fn search<C: Cost>(
+    own: usize,
+    target: Node<C>,
+    frontiers: Arc<[Mutex<Frontier<C>>; 2]>,
+    incumbent: Arc<Atomic<Incumbent<C>>>,
+    budget: Arc<Budget>,
+    finished: Arc<AtomicBool>,
+    flags: Flags,
+) -> SearchStats {
+    let other = 1 - own;
+    let mut stats = SearchStats::default();
+
+    loop {
+        if finished.load(Ordering::SeqCst) {
+            return stats;
+        }
+
+        if budget.exceeded() {
+            finished.store(true, Ordering::SeqCst);
+            return stats;
+        }
+
+        let mut frontier = frontiers[own].lock().unwrap();
+        frontier.prune();
+
+        let best = incumbent.load(Ordering::SeqCst).cost;
+        let expandable = match frontier.open.peek() {
+            Some(entry) => entry.priority < best,
+            None => false,
+        };
+
+        // Nothing useful to do on this side, see if the whole search is done.
+        if !expandable {
+            drop(frontier);
+
+            if should_stop(&frontiers, &incumbent) {
+                finished.store(true, Ordering::SeqCst);
+                return stats;
+            }
+
+            thread::yield_now();
+            continue;
+        }
+
+        let Entry { priority, node } = frontier.open.pop().unwrap();
+        frontier.seen.insert(node.position, (node, true));
+        frontier.expanding.push(priority);
+        budget.expanded();
+        stats.expansions += 1;
+
+        let adjacent = vec![(0, 1), (-1, 0), (1, 0), (0, -1)];
+        let mut generated = Vec::with_capacity(adjacent.len());
+
+        for (x, y) in adjacent {
+            if !helpers::is_valid_neighbor(&flags.graph, &node, x, y) {
+                continue;
+            }
+
+            let mut n_prime = Node::new(node.position.x + x, node.position.y + y, C::ZERO, node.g + C::from_u32(1), C::ZERO, node.position);
+
+            stats.generated += 1;
+
+            match frontier.seen.get(&n_prime.position) {
+                Some((known, _)) if known.g <= n_prime.g => {
+                    stats.duplicates += 1;
+                    continue;
+                },
+                Some((_, true)) => stats.reopened += 1,
+                _ => (),
+            }
+
+            n_prime.h = helpers::heuristic(n_prime, target, &*flags.heur);
+            stats.evaluations += 1;
+            n_prime.f = n_prime.g + n_prime.h;
+            frontier.seen.insert(n_prime.position, (n_prime, false));
+            frontier.open.push(Entry::new(n_prime));
+            budget.stored();
+            generated.push(n_prime);
+        }
+
+        // Only one frontier lock is held at a time, the meeting check runs after
+        // the insertions above so a node reached from both sides is always seen
+        // by at least one of the two threads.
+        drop(frontier);
+
+        let opposite = frontiers[other].lock().unwrap();
+
+        for n_prime in generated {
+            if let Some((met, _)) = opposite.seen.get(&n_prime.position) {
+                let cost = n_prime.g + met.g;
+                let mut current = incumbent.load(Ordering::SeqCst);
+
+                while cost < current.cost {
+                    match incumbent.compare_exchange(current, Incumbent::new(n_prime, cost), Ordering::SeqCst, Ordering::SeqCst) {
+                        Ok(_) => break,
+                        Err(actual) => current = actual,
+                    }
+                }
+            }
+        }
+
+        drop(opposite);
+
+        let mut frontier = frontiers[own].lock().unwrap();
+        let slot = frontier.expanding.iter().position(|p| *p == priority).unwrap();
+        frontier.expanding.swap_remove(slot);
+    }
+}
+
+/// Path through `meeting`: the forward half up to it, then the backward half
+/// walked back out to the goal.
+fn join_paths<C: Cost>(frontiers: &[Mutex<Frontier<C>>; 2], meeting: Point, limit: usize) -> Vec<Point> {
+    let halves: Vec<Vec<Point>> = frontiers.iter().map(|frontier| {
+        let frontier = frontier.lock().unwrap();
+
+        helpers::trace_path(meeting, limit, |point| frontier.seen.get(&point).map(|(node, _)| node.parent))
+    }).collect();
+
+    let mut path = halves[0].clone();
+    path.extend(halves[1].iter().rev().skip(1));
+    path
+}
+
+/// Bidirectional search with the forward and backward frontiers grown by
+/// disjoint thread groups. At least one thread is given to each direction,
+/// and each thread's statistics count the side it grows.
+pub fn setup<C: Cost>(start_point: Point, end_point: Point, flags: Flags) -> Solution<C> {
+    let Flags { heur, graph, threads: thread_cnt, limits, pool, .. } = flags;
+    let forward_cnt = cmp::max(1, thread_cnt.div_ceil(2));
+    let backward_cnt = cmp::max(1, thread_cnt / 2);
+    let workers = Workers::new(pool.as_deref(), None, forward_cnt + backward_cnt);
+    let mut threads = Vec::with_capacity(forward_cnt + backward_cnt);
+
+    let mut start = Node::at(start_point);
+    let mut end = Node::at(end_point);
+    start.h = helpers::heuristic(start, end, &*heur);
+    start.f = start.g + start.h;
+    end.h = helpers::heuristic(end, start, &*heur);
+    end.f = end.g + end.h;
+
+    let frontiers = Arc::new([Mutex::new(Frontier::new(start)), Mutex::new(Frontier::new(end))]);
+    let finished = Arc::new(AtomicBool::new(false));
+    let budget = Arc::new(Budget::new(limits));
+    let initial_cost = if start == end { C::ZERO } else { C::INFINITY };
+    let incumbent: Arc<Atomic<Incumbent<C>>> = Arc::new(Atomic::new(Incumbent::new(start, initial_cost)));
+
+    for i in 0..(forward_cnt + backward_cnt) {
+        // Forward threads aim for the goal, backward threads for the start.
+        let (own, target) = if i < forward_cnt { (0, end) } else { (1, start) };
+        let frontiers = frontiers.clone();
+        let incumbent = incumbent.clone();
+        let finished = finished.clone();
+        let budget = budget.clone();
+        let flags = Flags { graph: graph.clone(), heur: heur.clone(), threads: thread_cnt, ..Flags::default() };
+
+        threads.push(workers.spawn(i, move || {
+            search(own, target, frontiers, incumbent, budget, finished, flags)
+        }))
+    }
+
+    // Final answer is outputted once all threads are done.
+    let stats = threads.into_iter().map(|thread| thread.join().expect("Panic").1).collect();
+
+    let final_incumbent = incumbent.load(Ordering::SeqCst);
+    let path = join_paths(&frontiers, final_incumbent.node.position, graph.len() * graph.len());
+
+    Solution::new(final_incumbent.cost, path, budget.reason()).with_stats(stats)
+}
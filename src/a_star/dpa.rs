@@ -1,26 +1,83 @@
-use self::atomic::{AtomicU64, Ordering};
-use ::atomic::Atomic;
+use self::atomic::Ordering;
 use crossbeam::channel::{Sender, Receiver, unbounded};
 use std::{
-    thread,
+    cmp,
     mem::drop,
-    collections::{HashSet, BinaryHeap},
-    sync::{Arc, atomic}
+    collections::BinaryHeap,
+    sync::{Arc, atomic},
+    time::{Duration, Instant}
 };
 use super::utils::{
-    structs::{Incumbent, Node, Point, Buffer, Flags},
-    dynamic_barrier::DynamicHurdle,
+    pool::Workers,
+    structs::{Node, Point, Buffer, Flags, Solution, TieBreak, Tie},
+    cost::Cost,
+    open_list::OpenList,
+    distributed::Shared,
+    cell_table::{Cell, CellTable},
+    stats::{SearchStats, CommStats},
     helpers
 };
 
-pub fn setup(start_point: Point, end_point: Point, flags: Flags) {
-    let Flags { heur, graph, threads: thread_cnt } = flags;
+// How long an idle thread waits on its channel before checking for
+// termination again.
+const IDLE_WAIT: Duration = Duration::from_micros(100);
+
+/// Messages a thread has taken off its channel, handed out cheapest g first
+/// and equal g in the tie-break policy's order. Messages carry no h yet, so
+/// the g and h policies leave equal messages in no particular order.
+struct Inbox<C: Cost> {
+    waiting: BinaryHeap<Waiting<C>>,
+    tie_break: TieBreak,
+    seq: u64,
+}
+
+impl<C: Cost> Inbox<C> {
+    fn new(tie_break: TieBreak) -> Inbox<C> {
+        Inbox { waiting: BinaryHeap::new(), tie_break, seq: 0 }
+    }
+
+    fn push(&mut self, message: Buffer<C>) {
+        let Buffer(node, weight, _) = message;
+
+        self.waiting.push(Waiting(weight, self.tie_break.key(&node, self.seq), message));
+        self.seq += 1;
+    }
+
+    fn pop(&mut self) -> Option<Buffer<C>> {
+        self.waiting.pop().map(|Waiting(_, _, message)| message)
+    }
+}
+
+/// A message in an `Inbox` with its g and tie-break key. Lower sorts
+/// greater, as `BinaryHeap` expects.
+struct Waiting<C: Cost>(C, Tie<C>, Buffer<C>);
+
+impl<C: Cost> Ord for Waiting<C> {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        (other.0, other.1).cmp(&(self.0, self.1))
+    }
+}
+
+impl<C: Cost> PartialOrd for Waiting<C> {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<C: Cost> PartialEq for Waiting<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl<C: Cost> Eq for Waiting<C> {}
+
+pub fn setup<C: Cost>(start_point: Point, end_point: Point, flags: Flags) -> Solution<C> {
+    let Flags { heur, graph, threads: thread_cnt, mode, anytime, limits, successors, tie_break, pinning, pool, .. } = flags;
+    let mut workers = Workers::new(pool.as_deref(), pinning, thread_cnt);
     let mut threads = Vec::with_capacity(thread_cnt);
-    let mut receivers: Vec<Receiver<Buffer>> = Vec::with_capacity(thread_cnt);
-    let mut transmitters: Vec<Sender<Buffer>> = Vec::with_capacity(thread_cnt);
-    let mut barrier = DynamicHurdle::new(thread_cnt);
-    let sent_messages = Arc::new(AtomicU64::new(0));
-    let received_messages = Arc::new(AtomicU64::new(0));
+    let mut receivers: Vec<Receiver<Buffer<C>>> = Vec::with_capacity(thread_cnt);
+    let mut transmitters: Vec<Sender<Buffer<C>>> = Vec::with_capacity(thread_cnt);
 
     // Declares channels
     for _ in 0..thread_cnt {
@@ -30,27 +87,25 @@ pub fn setup(start_point: Point, end_point: Point, flags: Flags) {
         receivers.push(rx);
     }
 
-    let mut start = Node::new(start_point.x, start_point.y, 0, 0, 0, Point::default());
-    let end = Node::new(end_point.x, end_point.y, 0, 0, 0, Point::default());
-    start.h = helpers::heuristic(start, end, &flags.heur);
-    start.f = start.g + start.h;
-    let incumbent: Arc<Atomic<Incumbent>> = Arc::new(Atomic::new(Incumbent::new(start, i128::MAX)));
+    let mut start = Node::at(start_point);
+    let end = Node::at(end_point);
+    start.h = helpers::heuristic(start, end, &*heur);
+    start.f = mode.f(start.g, start.h);
+
+    let shared = Arc::new(Shared::new(start, thread_cnt, limits.clone(), workers.cells::<C>(&graph), graph.len()));
 
     // Here, we would give each thread a different node to start on.
     // Those threads would run A* on each of their respective start nodes.
-    for i in 0..thread_cnt {
+    for (i, rx) in receivers.iter().enumerate() {
         let transmitters = transmitters.clone();
-        let incumbent = incumbent.clone();
-        let graph = graph.clone();
-        let barrier = barrier.create();
-        let rx = receivers[i].clone();
-        let sent_messages = sent_messages.clone();
-        let received_messages = received_messages.clone();
-        let flags = Flags { graph: graph.clone(), heur, threads: thread_cnt };
+        let shared = shared.clone();
+        let rx = rx.clone();
+        let flags = Flags { graph: graph.clone(), heur: heur.clone(), threads: thread_cnt, mode, anytime: anytime.clone(), limits: limits.clone(),
+                            successors: successors.clone(), tie_break, ..Flags::default() };
 
         // Here we'd pass a start node to each thread.
-        threads.push(thread::spawn(move || {
-            search(start, i, rx, transmitters, barrier, end, incumbent, graph.clone(), sent_messages, received_messages, flags);
+        threads.push(workers.spawn(i, move || {
+            search(start, i, rx, transmitters, end, shared, flags)
         }))
     }
 
@@ -59,103 +114,106 @@ pub fn setup(start_point: Point, end_point: Point, flags: Flags) {
     drop(receivers);
 
     // Final answer is outputted once all threads are done.
-    for thread in threads {
-        thread.join().expect("Panic");
-    }
+    let (placement, (stats, comm)): (Vec<_>, (Vec<_>, Vec<_>)) = threads.into_iter().map(|thread| thread.join().expect("Panic")).unzip();
 
-    let final_incumbent = incumbent.load(Ordering::SeqCst);
+    let (cost, path) = shared.solution();
 
-    println!("All threads found goal node {},{}. Cost of {}",
-            final_incumbent.node.position.x, 
-            final_incumbent.node.position.y,
-            final_incumbent.cost);
+    Solution::new(cost, path, shared.budget.reason()).with_stats(stats).with_comm(comm).with_placement(placement)
 }
 
-// A* implementation
-fn search(start: Node, thread_num: usize, rx: Receiver<Buffer>, tx: Vec<Sender<Buffer>>,
-          mut barrier: DynamicHurdle, goal_node: Node, incumbent: Arc<Atomic<Incumbent>>,
-          _graph: Vec<Vec<char>>, sent_messages: Arc<AtomicU64>, received_messages: Arc<AtomicU64>,
-          flags: Flags) {
-    let mut buffer: BinaryHeap<Buffer> = BinaryHeap::new();
-    let mut closed_list: HashSet<Node> = HashSet::new();
-    let mut open: BinaryHeap<Node> = BinaryHeap::new();
-    let mut open_list: HashSet<Node> = HashSet::new();
-    let mut tried: HashSet<i32> = HashSet::new();	
-    let mut first_iteration: bool = true;
-    let mut exit: bool = false;
+// A* implementation. Threads run freely as in `hda`, but take in all their
+// messages cheapest first before each expansion.
+#[allow(clippy::too_many_arguments)]
+fn search<C: Cost>(start: Node<C>, thread_num: usize, rx: Receiver<Buffer<C>>, tx: Vec<Sender<Buffer<C>>>,
+          goal_node: Node<C>, shared: Arc<Shared<C>>, flags: Flags) -> (SearchStats, CommStats) {
+    let Shared { incumbent, sent_messages, working, .. } = &*shared;
+    let mut buffer = Inbox::new(flags.tie_break);
+    let mut cells = CellTable::new(shared.cells.clone());
+    let mut open = OpenList::new(flags.mode, flags.tie_break);
+    let mut stats = SearchStats::default();
+    let mut comm = CommStats::new(thread_num, flags.threads);
     
-    // Giving appropriate lists start variable.
-    open.push(start);
-    open_list.insert(start);
-    buffer.push(Buffer(start, 0, start));
+    // Only the start's owner begins with it, the rest wait for messages.
+    if helpers::compute_recipient(&start, flags.threads as u64) == thread_num {
+        open.push(start);
+        cells.set(start.position, Cell { g: start.g, closed: false });
+    }
 
     loop {
-        // Initial thread synchronization before checking for count and messages.
-        barrier.wait();
+        if shared.stop.load(Ordering::SeqCst) || shared.done.load(Ordering::SeqCst) {
+            return (stats, comm);
+        }
 
-        if !first_iteration && sent_messages.load(Ordering::SeqCst) == received_messages.load(Ordering::SeqCst) {			
-            break;
+        if shared.budget.exceeded() {
+            shared.stop.store(true, Ordering::SeqCst);
+            continue;
         }
 
-        // Barrier wait forces all threads to read the same d_me count.
-        barrier.wait();
-        first_iteration = false;
+        comm.queue_high_water = comm.queue_high_water.max(rx.len());
 
         // Loops until we have no more data to add to buffer list (no more messages received).
-        loop {
-            match rx.try_recv() {
-                Ok(v) => {
-                    received_messages.fetch_add(1, Ordering::SeqCst);
-                    buffer.push(v);
-                },
-                Err(_) => break,
-            }
+        while let Ok(v) = rx.try_recv() {
+            buffer.push(shared.receive(thread_num, v));
+            comm.received += 1;
         }
         
-        // Barrier is implicitly dropped, no need to drop it.
-        if exit {
-            drop(rx);
-            break;
-        }
 
         // Loop until buffer is empty.
-        while !buffer.is_empty() {
-            let Buffer(node, weight, parent) = buffer.pop().unwrap();
-
-            if closed_list.contains(&node) {
-                if closed_list.get(&node).unwrap().g > weight {
-                    closed_list.remove(&node);
-                }
-                else {
+        while let Some(Buffer(node, weight, parent)) = buffer.pop() {
+            match cells.get(node.position) {
+                Some(cell) if cell.g <= weight => {
+                    stats.duplicates += 1;
                     continue;
-                }
-            }
-            else {
-                if open_list.contains(&node) && open_list.get(&node).unwrap().g <= weight {
-                    continue;
-                }
-                else {
-                    open_list.remove(&node);
-                }
+                },
+                Some(Cell { closed: true, .. }) => stats.reopened += 1,
+                _ => (),
             }
-            
+
             // Open list is updated with new node values. 
             let mut new_node = Node { g: weight, parent: parent.position, ..node };
-            new_node.h = helpers::heuristic(new_node, goal_node, &flags.heur);
-            new_node.f = new_node.g + new_node.h;
-            open_list.insert(new_node);
+            new_node.h = helpers::heuristic(new_node, goal_node, &*flags.heur);
+            stats.evaluations += 1;
+            new_node.f = flags.mode.f(new_node.g, new_node.h);
+            shared.budget.stored();
+            cells.set(new_node.position, Cell { g: new_node.g, closed: false });
             open.push(new_node);
         }
 
-        if open.is_empty() || open.peek().unwrap().f >= incumbent.load(Ordering::SeqCst).cost {
+        // Nothing here can improve the incumbent, so go idle until a message
+        // arrives or every thread is idle.
+        if !shared.has_work(&open, &flags) {
+            comm.idle_iterations += 1;
+            working[thread_num].store(false, Ordering::SeqCst);
+
+            if shared.terminated() {
+                shared.done.store(true, Ordering::SeqCst);
+                continue;
+            }
+
+            let started = Instant::now();
+
+            if let Ok(v) = rx.recv_timeout(IDLE_WAIT) {
+                buffer.push(shared.receive(thread_num, v));
+                comm.received += 1;
+            }
+
+            comm.blocked += started.elapsed();
             continue;
         }
         
         // Process node to see if it's goal node.
         let temp_node = open.pop().unwrap();
 
-        open_list.remove(&temp_node);
-        closed_list.insert(temp_node);
+        if shared.stale(&temp_node, &flags) {
+            continue;
+        }
+
+        shared.budget.expanded();
+        stats.expansions += 1;
+        cells.set(temp_node.position, Cell { g: temp_node.g, closed: true });
+        // Only expanded nodes are linked in, so a node improved by a message
+        // but never expanded cannot reroute the path to a goal behind it.
+        shared.parents.insert(temp_node.position, temp_node.parent);
         
         while temp_node == goal_node && incumbent.load(Ordering::SeqCst).cost > temp_node.g {
             let temp = incumbent.load(Ordering::SeqCst);
@@ -164,47 +222,32 @@ fn search(start: Node, thread_num: usize, rx: Receiver<Buffer>, tx: Vec<Sender<B
                 let mut new_incumbent = temp;
                 new_incumbent.node = temp_node;
                 new_incumbent.cost = temp_node.g;
-                match incumbent.compare_exchange(temp, new_incumbent, Ordering::SeqCst, Ordering::SeqCst) {
-                    Ok(_) => {
-                        exit = true;
-                    },
-                    Err(_) => {
-                    }
+                let won = incumbent.compare_exchange(temp, new_incumbent, Ordering::SeqCst, Ordering::SeqCst).is_ok();
+
+                if won {
+                    shared.record(temp_node.position, flags.anytime.as_ref());
                 }
             }
         }
         
-        let adjacent = vec![(0, 1), (-1, 0), (1, 0), (0, -1)];        
-
-        // First check if valid move, then We will offset to n', and pass off three-tuple
-        // to random thread's buffer list.
-        for (x, y) in adjacent {
-            // Safe guard before we test a movement
-            if helpers::is_valid_neighbor(&flags.graph, &temp_node, x, y) {
-                // n' is created, now let's put it in a random buffered list.
-                let (x_coordinate, y_coordinate) = (temp_node.position.x + x, temp_node.position.y + y);
-                let n_prime = Node::new(x_coordinate, y_coordinate, 0, temp_node.g + 1, 0, temp_node.position);
-                
-                loop {
-                    let i = helpers::compute_recipient(&n_prime, &tried, flags.threads as u64, thread_num); // calculate hash of node to send to a thread.
-                    
-                    match tx[i as usize].send(Buffer(n_prime, n_prime.g, temp_node)) {
-                        Ok(_) => {
-                            sent_messages.fetch_add(1, Ordering::SeqCst);
-                            break;
-                        },
-                        Err(_) => {
-                            tried.insert(i as i32);
-                            
-                            if tried.len() < flags.threads as usize {
-                                continue;
-                            }
-                            else {
-                                break;
-                            }
-                        },
-                    }
-                }
+        // Pass each n' off as a three-tuple to the buffer list of the thread that owns it.
+        for (position, cost) in flags.successors.expand(&flags.graph, &temp_node, goal_node.position) {
+            let n_prime = Node::new(position.x, position.y, C::ZERO, temp_node.g + cost, C::ZERO, temp_node.position);
+            let i = helpers::compute_recipient(&n_prime, flags.threads as u64);
+
+            // Counted before it goes out so receipts never run ahead of sends.
+            sent_messages.fetch_add(1, Ordering::SeqCst);
+
+            // Owners only hang up once the search is over, so the node is no longer needed.
+            match tx[i].send(Buffer(n_prime, n_prime.g, temp_node)) {
+                Ok(_) => {
+                    stats.generated += 1;
+                    comm.sent[i] += 1;
+                    comm.batches += 1;
+                },
+                Err(_) => {
+                    sent_messages.fetch_sub(1, Ordering::SeqCst);
+                },
             }
         }
     }
@@ -1,26 +1,31 @@
-use self::atomic::{AtomicU64, Ordering};
-use ::atomic::Atomic;
+use self::atomic::Ordering;
 use crossbeam::channel::{Sender, Receiver, unbounded};
 use std::{
-    thread,
-    mem::drop,
-    collections::{HashSet, BinaryHeap},
-    sync::{Arc, atomic}
+    mem::{drop, take},
+    sync::{Arc, atomic},
+    time::{Duration, Instant}
 };
 use super::utils::{
-	structs::{Incumbent, Node, Point, Buffer, Flags},
-	dynamic_barrier::DynamicHurdle,
+    pool::Workers,
+	structs::{Node, Point, Buffer, Flags, Solution},
+    cost::Cost,
+    open_list::OpenList,
+    distributed::Shared,
+    cell_table::{Cell, CellTable},
+    stats::{SearchStats, CommStats},
     helpers
 };
 
-pub fn setup(start_point: Point, end_point: Point, flags: Flags) {
-    let Flags { heur, graph, threads: thread_cnt } = flags;
+// How long an idle thread waits on its channel before checking for
+// termination again.
+const IDLE_WAIT: Duration = Duration::from_micros(100);
+
+pub fn setup<C: Cost>(start_point: Point, end_point: Point, flags: Flags) -> Solution<C> {
+    let Flags { heur, graph, threads: thread_cnt, mode, anytime, limits, successors, batching, tie_break, pinning, pool, .. } = flags;
+    let mut workers = Workers::new(pool.as_deref(), pinning, thread_cnt);
     let mut threads = Vec::with_capacity(thread_cnt);
-    let mut receivers: Vec<Receiver<Buffer>> = Vec::with_capacity(thread_cnt);
-    let mut transmitters: Vec<Sender<Buffer>> = Vec::with_capacity(thread_cnt);
-    let mut barrier = DynamicHurdle::new(thread_cnt);
-    let sent_messages = Arc::new(AtomicU64::new(0));
-    let received_messages = Arc::new(AtomicU64::new(0));
+    let mut receivers: Vec<Receiver<Vec<Buffer<C>>>> = Vec::with_capacity(thread_cnt);
+    let mut transmitters: Vec<Sender<Vec<Buffer<C>>>> = Vec::with_capacity(thread_cnt);
 
     // Declares channels
     for _ in 0..thread_cnt {
@@ -30,27 +35,25 @@ pub fn setup(start_point: Point, end_point: Point, flags: Flags) {
         receivers.push(rx);
     }
 
-    let mut start = Node::new(start_point.x, start_point.y, 0, 0, 0, Point::default());
-    let end = Node::new(end_point.x, end_point.y, 0, 0, 0, Point::default());
-    start.h = helpers::heuristic(start, end, &flags.heur);
-    start.f = start.g + start.h;
-    let incumbent: Arc<Atomic<Incumbent>> = Arc::new(Atomic::new(Incumbent::new(start, i128::MAX)));
+    let mut start = Node::at(start_point);
+    let end = Node::at(end_point);
+    start.h = helpers::heuristic(start, end, &*heur);
+    start.f = mode.f(start.g, start.h);
+
+    let shared = Arc::new(Shared::new(start, thread_cnt, limits.clone(), workers.cells::<C>(&graph), graph.len()));
+
     // Here, we would give each thread a different node to start on.
     // Those threads would run A* on each of their respective start nodes.
-    for i in 0..thread_cnt {
+    for (i, rx) in receivers.iter().enumerate() {
         let transmitters = transmitters.clone();
-        let incumbent = incumbent.clone();
-        let barrier = barrier.create();
-        let rx = receivers[i].clone();
-        let sent_messages = sent_messages.clone();
-        let received_messages = received_messages.clone();
-        let flags = Flags { graph: graph.clone(), heur, threads: thread_cnt };
-        let id = i.clone();
+        let shared = shared.clone();
+        let rx = rx.clone();
+        let flags = Flags { graph: graph.clone(), heur: heur.clone(), threads: thread_cnt, mode, anytime: anytime.clone(), limits: limits.clone(),
+                            successors: successors.clone(), batching, tie_break, ..Flags::default() };
 
         // Here we'd pass a start node to each thread.
-        threads.push(thread::spawn(move || {
-            search(start, i, rx, transmitters, barrier, end,
-                   incumbent, sent_messages, received_messages, flags, id);
+        threads.push(workers.spawn(i, move || {
+            search(start, i, rx, transmitters, end, shared, flags)
         }))
     }
 
@@ -59,91 +62,143 @@ pub fn setup(start_point: Point, end_point: Point, flags: Flags) {
     drop(receivers);
 
     // Final answer is outputted once all threads are done.
-    for thread in threads {
-        thread.join().expect("Panic");
-    }
+    let (placement, (stats, comm)): (Vec<_>, (Vec<_>, Vec<_>)) = threads.into_iter().map(|thread| thread.join().expect("Panic")).unzip();
 
-    let final_incumbent = incumbent.load(Ordering::SeqCst);
+    let (cost, path) = shared.solution();
 
-    println!("All threads found goal node {},{}. Cost of {}", final_incumbent.node.position.x, 
-                                    final_incumbent.node.position.y,
-                                    final_incumbent.cost);
+    Solution::new(cost, path, shared.budget.reason()).with_stats(stats).with_comm(comm).with_placement(placement)
+}
+
+/// Sends the nodes queued for thread `to`, if any, as one batch.
+fn flush<C: Cost>(to: usize, outbox: &mut [Vec<Buffer<C>>], tx: &[Sender<Vec<Buffer<C>>>], shared: &Shared<C>, comm: &mut CommStats) {
+    if outbox[to].is_empty() {
+        return;
+    }
+
+    let batch = take(&mut outbox[to]);
+    let nodes = batch.len() as u64;
+
+    // Counted before it goes out so receipts never run ahead of sends.
+    shared.sent_messages.fetch_add(1, Ordering::SeqCst);
+
+    // Owners only hang up once the search is over, so the nodes are no longer needed.
+    match tx[to].send(batch) {
+        Ok(_) => {
+            comm.sent[to] += nodes;
+            comm.batches += 1;
+        },
+        Err(_) => {
+            shared.sent_messages.fetch_sub(1, Ordering::SeqCst);
+        },
+    }
 }
 
-fn search(start: Node, thread_num: usize, rx: Receiver<Buffer>, tx: Vec<Sender<Buffer>>,
-          mut barrier: DynamicHurdle, goal_node: Node, incumbent: Arc<Atomic<Incumbent>>,
-          sent_messages: Arc<AtomicU64>, received_messages: Arc<AtomicU64>, flags: Flags, _id: usize) {
-    let mut closed_list: HashSet<Node> = HashSet::new();
-    let mut open: BinaryHeap<Node> = BinaryHeap::new();
-    let mut open_list: HashSet<Node> = HashSet::new();
-    let mut tried: HashSet<i32> = HashSet::new();	
-    let mut first_iteration: bool = true;
-    let mut exit: bool = false;
+/// One HDA* thread. Threads run freely, expanding whenever they hold a node
+/// that could improve the incumbent, and only stop once a limit is hit or
+/// `Shared::terminated` sees every thread idle with nothing in flight.
+#[allow(clippy::too_many_arguments)]
+fn search<C: Cost>(start: Node<C>, thread_num: usize, rx: Receiver<Vec<Buffer<C>>>, tx: Vec<Sender<Vec<Buffer<C>>>>,
+          goal_node: Node<C>, shared: Arc<Shared<C>>, flags: Flags) -> (SearchStats, CommStats) {
+    let Shared { incumbent, working, .. } = &*shared;
+    let mut cells = CellTable::new(shared.cells.clone());
+    let mut open = OpenList::new(flags.mode, flags.tie_break);
+    let mut stats = SearchStats::default();
+    let mut comm = CommStats::new(thread_num, flags.threads);
+    // Batch picked up while waiting idle, handled with the rest.
+    let mut waiting: Option<Vec<Buffer<C>>> = None;
+    // Nodes not yet sent, by owner, and when the oldest of them was queued.
+    let mut outbox: Vec<Vec<Buffer<C>>> = vec![Vec::new(); flags.threads];
+    let mut oldest: Option<Instant> = None;
     
-    // Giving appropriate lists start variable.
-    open.push(start);
-    open_list.insert(start);
+    // Only the start's owner begins with it, the rest wait for messages.
+    if helpers::compute_recipient(&start, flags.threads as u64) == thread_num {
+        open.push(start);
+        cells.set(start.position, Cell { g: start.g, closed: false });
+    }
 
     loop {
-        // Initial thread synchronization before checking for count and messages.
-        barrier.wait();
-        
-        if !first_iteration && sent_messages.load(Ordering::SeqCst) == received_messages.load(Ordering::SeqCst) {			
-            break;
+        if shared.stop.load(Ordering::SeqCst) || shared.done.load(Ordering::SeqCst) {
+            return (stats, comm);
         }
-        
-        // Barrier wait forces all threads to read the same receivedMessage count.
-        barrier.wait();
-        first_iteration = false;
+
+        if shared.budget.exceeded() {
+            shared.stop.store(true, Ordering::SeqCst);
+            continue;
+        }
+
+        comm.queue_high_water = comm.queue_high_water.max(rx.len() + waiting.is_some() as usize);
 
         // Loops until we have no more data to add to buffer list (no more messages received).
-        loop {
-            match rx.try_recv() {
-                Ok(Buffer(node, weight, parent)) => {
-                    received_messages.fetch_add(1, Ordering::SeqCst);
-                    
-                    if closed_list.contains(&node) {
-                        if closed_list.get(&node).unwrap().g > weight {
-                            closed_list.remove(&node);
-                        }
-                        else {
-                            continue;
-                        }
-                    }
-                    else {
-                        if open_list.contains(&node) && open_list.get(&node).unwrap().g <= weight {
-                            continue;
-                        }
-                        else {
-                            open_list.remove(&node);
-                        }
-                    }
-
-                    let mut new_node = Node { g: weight, parent: parent.position, ..node };
-                    new_node.h = helpers::heuristic(new_node, goal_node, &flags.heur);
-                    new_node.f = new_node.g + new_node.h;
-                    open_list.insert(new_node);
-                    open.push(new_node);
-                },
-                Err(_) => break,
+        while let Some(batch) = waiting.take().or_else(|| rx.try_recv().ok().map(|m| shared.receive(thread_num, m))) {
+            comm.received += batch.len() as u64;
+
+            for Buffer(node, weight, parent) in batch {
+                match cells.get(node.position) {
+                    Some(cell) if cell.g <= weight => {
+                        stats.duplicates += 1;
+                        continue;
+                    },
+                    Some(Cell { closed: true, .. }) => stats.reopened += 1,
+                    _ => (),
+                }
+
+                let mut new_node = Node { g: weight, parent: parent.position, ..node };
+                new_node.h = helpers::heuristic(new_node, goal_node, &*flags.heur);
+                stats.evaluations += 1;
+                new_node.f = flags.mode.f(new_node.g, new_node.h);
+                shared.budget.stored();
+                cells.set(new_node.position, Cell { g: new_node.g, closed: false });
+                open.push(new_node);
             }
         }
 
-        // Barrier is implicitly dropped, no need to drop it.
-        if exit {
-            drop(rx);
-            break;
+        // Nodes held back too long slow the search more than sending them costs.
+        if oldest.is_some_and(|queued| queued.elapsed() >= flags.batching.wait) {
+            for to in 0..flags.threads {
+                flush(to, &mut outbox, &tx, &shared, &mut comm);
+            }
+
+            oldest = None;
         }
-        
-        if open.is_empty() || open.peek().unwrap().f >= incumbent.load(Ordering::SeqCst).cost {
+
+        // Nothing here can improve the incumbent, so go idle until a message
+        // arrives or every thread is idle.
+        if !shared.has_work(&open, &flags) {
+            comm.idle_iterations += 1;
+
+            // Everything queued has to be in flight before this thread counts as
+            // idle, or termination could be detected with nodes still held here.
+            for to in 0..flags.threads {
+                flush(to, &mut outbox, &tx, &shared, &mut comm);
+            }
+
+            oldest = None;
+            working[thread_num].store(false, Ordering::SeqCst);
+
+            if shared.terminated() {
+                shared.done.store(true, Ordering::SeqCst);
+                continue;
+            }
+
+            let started = Instant::now();
+            waiting = rx.recv_timeout(IDLE_WAIT).ok().map(|m| shared.receive(thread_num, m));
+            comm.blocked += started.elapsed();
             continue;
         }
         
         // Process node to see if it's goal node.
         let temp_node = open.pop().unwrap();
 
-        open_list.remove(&temp_node);
-        closed_list.insert(temp_node);
+        if shared.stale(&temp_node, &flags) {
+            continue;
+        }
+
+        shared.budget.expanded();
+        stats.expansions += 1;
+        cells.set(temp_node.position, Cell { g: temp_node.g, closed: true });
+        // Only expanded nodes are linked in, so a node improved by a message
+        // but never expanded cannot reroute the path to a goal behind it.
+        shared.parents.insert(temp_node.position, temp_node.parent);
 
         while temp_node == goal_node && incumbent.load(Ordering::SeqCst).cost > temp_node.g {
             let temp = incumbent.load(Ordering::SeqCst);
@@ -152,47 +207,26 @@ fn search(start: Node, thread_num: usize, rx: Receiver<Buffer>, tx: Vec<Sender<B
                 let mut new_incumbent = temp;
                 new_incumbent.node = temp_node;
                 new_incumbent.cost = temp_node.g;
-                match incumbent.compare_exchange(temp, new_incumbent, Ordering::SeqCst, Ordering::SeqCst) {
-                    Ok(_) => {
-                        exit = true;
-                    },
-                    Err(_) => {
-                    }
+                let won = incumbent.compare_exchange(temp, new_incumbent, Ordering::SeqCst, Ordering::SeqCst).is_ok();
+
+                if won {
+                    shared.record(temp_node.position, flags.anytime.as_ref());
                 }
             }
         }
         
-        let adjacent = vec![(0, 1), (-1, 0), (1, 0), (0, -1)];
-
-        // First check if valid move, then We will offset to n', and pass off three-tuple
-        // to random thread's buffer list.
-        for (x, y) in adjacent {
-            // Safe guard before we test a movement
-            if helpers::is_valid_neighbor(&flags.graph, &temp_node, x, y) {
-                // n' is created, now let's put it in a random buffered list.
-                let (x_coordinate, y_coordinate) = (temp_node.position.x + x, temp_node.position.y + y);
-                let n_prime = Node::new(x_coordinate, y_coordinate, 0, temp_node.g + 1, 0, temp_node.position);
-                
-                loop {
-                    let i = helpers::compute_recipient(&n_prime, &tried, flags.threads as u64, thread_num);
-
-                    match tx[i as usize].send(Buffer(n_prime, n_prime.g, temp_node)) {
-                        Ok(_) => {
-                            sent_messages.fetch_add(1, Ordering::SeqCst);
-                            break;
-                        },
-                        Err(_) => {
-                            tried.insert(i as i32);
-                            
-                            if tried.len() < flags.threads as usize {
-                                continue;
-                            }
-                            else {
-                                break;
-                            }
-                        },
-                    }
-                }
+        // Successors are offset to n', and queued as a three-tuple for the
+        // owning thread's buffer list.
+        for (position, cost) in flags.successors.expand(&flags.graph, &temp_node, goal_node.position) {
+            let n_prime = Node::new(position.x, position.y, C::ZERO, temp_node.g + cost, C::ZERO, temp_node.position);
+            let i = helpers::compute_recipient(&n_prime, flags.threads as u64);
+
+            outbox[i].push(Buffer(n_prime, n_prime.g, temp_node));
+            oldest.get_or_insert_with(Instant::now);
+            stats.generated += 1;
+
+            if outbox[i].len() >= flags.batching.size {
+                flush(i, &mut outbox, &tx, &shared, &mut comm);
             }
         }
     }
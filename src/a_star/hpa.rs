@@ -0,0 +1,313 @@
+use std::{
+    cmp,
+    collections::{BinaryHeap, HashMap, VecDeque},
+    sync::Arc
+};
+use super::utils::{
+    structs::{Node, Point, Flags, Solution},
+    cost::Cost,
+    heuristics::Heuristic,
+    limits::{Budget, Limits},
+    helpers
+};
+
+/// Cluster side used by `setup`.
+pub const DEFAULT_CLUSTER_SIZE: usize = 16;
+// Entrances at least this wide get a transition at each end instead of one
+// in the middle.
+const WIDE_ENTRANCE: usize = 6;
+
+/// A square block of cells, bounds exclusive at the bottom and right.
+#[derive(Clone, Copy)]
+struct Cluster {
+    top: i32,
+    left: i32,
+    bottom: i32,
+    right: i32,
+}
+
+impl Cluster {
+    fn contains(&self, point: Point) -> bool {
+        point.x >= self.top && point.x < self.bottom && point.y >= self.left && point.y < self.right
+    }
+
+    fn local(&self, point: Point) -> usize {
+        ((point.x - self.top) * (self.right - self.left) + point.y - self.left) as usize
+    }
+
+    fn point(&self, local: usize) -> Point {
+        let width = (self.right - self.left) as usize;
+
+        Point { x: self.top + (local / width) as i32, y: self.left + (local % width) as i32 }
+    }
+
+    fn cells(&self) -> usize {
+        ((self.bottom - self.top) * (self.right - self.left)) as usize
+    }
+}
+
+/// Breadth-first search from `from` that never leaves `cluster`. Gives each
+/// reached cell, by local index, its distance and the cell it was reached from.
+fn cluster_bfs(graph: &[Vec<char>], cluster: Cluster, from: Point) -> Vec<Option<(u32, usize)>> {
+    let adjacent = [(0, 1), (-1, 0), (1, 0), (0, -1)];
+    let mut reached = vec![None; cluster.cells()];
+    let mut queue = VecDeque::from([from]);
+    reached[cluster.local(from)] = Some((0, cluster.local(from)));
+
+    while let Some(point) = queue.pop_front() {
+        let (distance, _) = reached[cluster.local(point)].unwrap();
+        let node = Node::<u32>::at(point);
+
+        for (x, y) in adjacent.iter() {
+            let next = Point { x: point.x + x, y: point.y + y };
+
+            if !cluster.contains(next) || !helpers::is_valid_neighbor(graph, &node, *x, *y) || reached[cluster.local(next)].is_some() {
+                continue;
+            }
+
+            reached[cluster.local(next)] = Some((distance + 1, cluster.local(point)));
+            queue.push_back(next);
+        }
+    }
+
+    reached
+}
+
+/// Abstract entry in the query's open list.
+#[derive(PartialEq, Eq)]
+struct Entry<C: Cost>(C, C, Point);
+
+impl<C: Cost> Ord for Entry<C> {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        other.0.cmp(&self.0)
+    }
+}
+
+impl<C: Cost> PartialOrd for Entry<C> {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Precomputed HPA* abstraction of one graph. The graph is cut into square
+/// clusters, every entrance between two neighbouring clusters gets one or two
+/// transitions, and the cells either side of a transition become nodes of an
+/// abstract graph, linked across the border at a cost of 1 and to the other
+/// nodes of their cluster at their in-cluster distance. Building it is the
+/// expensive part; after that it answers any number of queries on the graph.
+pub struct HpaIndex {
+    graph: Arc<Vec<Vec<char>>>,
+    cluster_size: usize,
+    // Clusters per side.
+    span: usize,
+    // Abstract nodes of each cluster, row-major.
+    nodes: Vec<Vec<Point>>,
+    edges: HashMap<Point, Vec<(Point, u32)>>,
+}
+
+impl HpaIndex {
+    /// Builds the index with `threads` threads, each taking a share of the
+    /// clusters in turn for entrances and then for in-cluster distances.
+    /// The graph is shared with the index rather than copied.
+    pub fn new(graph: Arc<Vec<Vec<char>>>, cluster_size: usize, threads: usize) -> HpaIndex {
+        let cluster_size = cmp::max(cluster_size, 1);
+        let span = graph.len().div_ceil(cluster_size);
+        let mut index = HpaIndex { graph, cluster_size, span, nodes: vec![Vec::new(); span * span],
+                                   edges: HashMap::new() };
+        let ids: Vec<usize> = (0..span * span).collect();
+
+        // Each cluster finds the transitions on its bottom and right borders.
+        let transitions = helpers::in_parallel(&ids, threads, |id| index.transitions(*id));
+
+        for (inside, outside) in transitions.into_iter().flatten() {
+            for (from, to) in [(inside, outside), (outside, inside)] {
+                let id = index.cluster_id(from);
+                let nodes = &mut index.nodes[id];
+
+                if !nodes.contains(&from) {
+                    nodes.push(from);
+                }
+
+                index.edges.entry(from).or_default().push((to, 1));
+            }
+        }
+
+        let links = helpers::in_parallel(&ids, threads, |id| {
+            let cluster = index.cluster(*id);
+
+            index.nodes[*id].iter()
+                .map(|from| (*from, index.links(cluster, *from, &index.nodes[*id])))
+                .collect::<Vec<_>>()
+        });
+
+        for (from, links) in links.into_iter().flatten() {
+            index.edges.entry(from).or_default().extend(links);
+        }
+
+        index
+    }
+
+    fn cluster_id(&self, point: Point) -> usize {
+        point.x as usize / self.cluster_size * self.span + point.y as usize / self.cluster_size
+    }
+
+    fn cluster(&self, id: usize) -> Cluster {
+        let (row, column) = ((id / self.span * self.cluster_size) as i32, (id % self.span * self.cluster_size) as i32);
+        let (size, edge) = (self.cluster_size as i32, self.graph.len() as i32);
+
+        Cluster { top: row, left: column, bottom: cmp::min(row + size, edge), right: cmp::min(column + size, edge) }
+    }
+
+    fn open(&self, point: Point) -> bool {
+        self.graph[point.x as usize][point.y as usize] != 'W'
+    }
+
+    /// Transitions from cluster `id` into the clusters below and to the right
+    /// of it, as pairs of cells facing each other across the border.
+    fn transitions(&self, id: usize) -> Vec<(Point, Point)> {
+        let cluster = self.cluster(id);
+        let edge = self.graph.len() as i32;
+        let mut transitions = Vec::new();
+
+        // The border cells on this side along with the step across.
+        let borders = [
+            ((cluster.left..cluster.right).map(|y| Point { x: cluster.bottom - 1, y }).collect::<Vec<_>>(), (1, 0)),
+            ((cluster.top..cluster.bottom).map(|x| Point { x, y: cluster.right - 1 }).collect::<Vec<_>>(), (0, 1)),
+        ];
+
+        for (cells, (dx, dy)) in borders.iter() {
+            let mut run: Vec<Point> = Vec::new();
+
+            for (i, cell) in cells.iter().enumerate() {
+                let across = Point { x: cell.x + dx, y: cell.y + dy };
+                let passable = across.x < edge && across.y < edge && self.open(*cell) && self.open(across);
+
+                if passable {
+                    run.push(*cell);
+                }
+
+                // A run of facing open cells ends here, place its transitions.
+                if !run.is_empty() && (!passable || i + 1 == cells.len()) {
+                    let picks = if run.len() < WIDE_ENTRANCE { vec![run[run.len() / 2]] } else { vec![run[0], run[run.len() - 1]] };
+
+                    for cell in picks {
+                        transitions.push((cell, Point { x: cell.x + dx, y: cell.y + dy }));
+                    }
+
+                    run.clear();
+                }
+            }
+        }
+
+        transitions
+    }
+
+    /// In-cluster distances from `from` to each of `targets` it can reach.
+    fn links(&self, cluster: Cluster, from: Point, targets: &[Point]) -> Vec<(Point, u32)> {
+        let reached = cluster_bfs(&self.graph, cluster, from);
+
+        targets.iter()
+            .filter(|target| **target != from)
+            .filter_map(|target| reached[cluster.local(*target)].map(|(distance, _)| (*target, distance)))
+            .collect()
+    }
+
+    /// Cells from `from` to `to`, both ends included. Either the two are
+    /// neighbours, possibly across a border, or they share a cluster.
+    fn refine(&self, from: Point, to: Point) -> Vec<Point> {
+        if (from.x - to.x).abs() + (from.y - to.y).abs() == 1 {
+            return vec![from, to];
+        }
+
+        let cluster = self.cluster(self.cluster_id(from));
+        let reached = cluster_bfs(&self.graph, cluster, from);
+        let mut path = vec![to];
+        let mut current = cluster.local(to);
+
+        while let Some((distance, parent)) = reached[current] {
+            if distance == 0 {
+                break;
+            }
+
+            path.push(cluster.point(parent));
+            current = parent;
+        }
+
+        path.reverse();
+        path
+    }
+
+    /// Answers one query: connects start and goal to the abstract nodes of
+    /// their clusters, runs A* over the abstract graph and refines the result
+    /// into cells. Paths are usually within a few percent of optimal, not
+    /// guaranteed optimal.
+    pub fn search<C: Cost>(&self, start_point: Point, end_point: Point, heur: &dyn Heuristic, limits: Limits) -> Solution<C> {
+        let budget = Budget::new(limits);
+        let end = Node::at(end_point);
+        let estimate = |point: Point| helpers::heuristic(Node::at(point), end, heur);
+
+        let start_id = self.cluster_id(start_point);
+        let end_id = self.cluster_id(end_point);
+        let mut start_links = self.links(self.cluster(start_id), start_point, &self.nodes[start_id]);
+        let end_links: HashMap<Point, u32> = self.links(self.cluster(end_id), end_point, &self.nodes[end_id]).into_iter().collect();
+
+        // Start and goal sharing a cluster may be joined inside it.
+        if start_id == end_id {
+            start_links.extend(self.links(self.cluster(start_id), start_point, &[end_point]));
+        }
+
+        let mut open = BinaryHeap::from([Entry(estimate(start_point), C::ZERO, start_point)]);
+        let mut best: HashMap<Point, (C, Point)> = HashMap::from([(start_point, (C::ZERO, Point::default()))]);
+
+        while let Some(Entry(_, g, point)) = open.pop() {
+            if budget.exceeded() {
+                break;
+            }
+
+            if best[&point].0 < g {
+                continue;
+            }
+
+            budget.expanded();
+
+            if point == end_point {
+                let corners = helpers::trace_path(point, best.len(), |point| best.get(&point).map(|(_, parent)| *parent));
+                let mut path = vec![start_point];
+
+                for pair in corners.windows(2) {
+                    path.extend(self.refine(pair[0], pair[1]).into_iter().skip(1));
+                }
+
+                return Solution::new(g, path, budget.reason());
+            }
+
+            let outgoing = self.edges.get(&point).into_iter().flatten()
+                .chain(if point == start_point { start_links.iter() } else { [].iter() })
+                .copied()
+                .chain(end_links.get(&point).map(|distance| (end_point, *distance)));
+
+            for (next, cost) in outgoing {
+                let next_g = g + C::from_u32(cost);
+
+                if best.get(&next).is_some_and(|(known, _)| *known <= next_g) {
+                    continue;
+                }
+
+                best.insert(next, (next_g, point));
+                budget.stored();
+                open.push(Entry(next_g + estimate(next), next_g, next));
+            }
+        }
+
+        Solution::new(C::INFINITY, Vec::new(), budget.reason())
+    }
+}
+
+/// Builds an index with `DEFAULT_CLUSTER_SIZE` clusters and answers a single
+/// query with it. Callers with many queries on one graph should build an
+/// `HpaIndex` once and call `HpaIndex::search` instead.
+pub fn setup<C: Cost>(start_point: Point, end_point: Point, flags: Flags) -> Solution<C> {
+    let Flags { heur, graph, threads, limits, .. } = flags;
+
+    HpaIndex::new(graph, DEFAULT_CLUSTER_SIZE, threads).search(start_point, end_point, &*heur, limits)
+}
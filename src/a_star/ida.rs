@@ -0,0 +1,340 @@
+use std::{
+    thread,
+    cmp,
+    collections::{HashSet, hash_map::DefaultHasher},
+    hash::{Hash, Hasher},
+    sync::Mutex,
+    sync::atomic::{AtomicBool, Ordering}
+};
+use super::utils::{
+    structs::{Node, Point, Flags, Solution},
+    cost::Cost,
+    heuristics::Heuristic,
+    limits::Budget,
+    stats::SearchStats,
+    helpers
+};
+
+// Budget limits are polled once per this many expansions by each thread.
+const CHECK_INTERVAL: u64 = 1024;
+// Subtrees handed out per thread by the parallel splitter.
+const SUBTREES_PER_THREAD: usize = 8;
+
+/// Direct-mapped transposition table for grids. Its size is fixed up front,
+/// colliding entries simply replace each other, and entries from earlier
+/// iterations are ignored rather than cleared.
+struct Transpositions<C: Cost> {
+    slots: Vec<(Point, C, u32)>,
+    iteration: u32,
+    // Slots written at least once, each counted against the budget's entries.
+    occupied: u64,
+}
+
+impl<C: Cost> Transpositions<C> {
+    fn new(capacity: usize) -> Transpositions<C> {
+        Transpositions { slots: vec![(Point::default(), C::ZERO, 0); cmp::max(capacity, 1)], iteration: 0, occupied: 0 }
+    }
+
+    fn next_iteration(&mut self) {
+        self.iteration += 1;
+    }
+
+    /// Whether `point` was already reached this iteration with a g no larger,
+    /// in which case everything below it has been or is being searched.
+    /// Otherwise records it, counting a slot never written before as stored.
+    fn seen(&mut self, point: Point, g: C, budget: &Budget) -> bool {
+        let mut state = DefaultHasher::new();
+        point.hash(&mut state);
+
+        let index = (state.finish() % self.slots.len() as u64) as usize;
+        let (known, known_g, iteration) = self.slots[index];
+
+        if known == point && iteration == self.iteration && known_g <= g {
+            return true;
+        }
+
+        // Iterations count from 1, so 0 marks a slot never written.
+        if iteration == 0 {
+            self.occupied += 1;
+            budget.stored();
+        }
+
+        self.slots[index] = (point, g, self.iteration);
+        false
+    }
+}
+
+/// Path from the start to a subtree's root, and the root itself.
+type Subtree<C> = (Vec<Point>, Node<C>);
+
+/// Result of one cost-bounded depth-first pass.
+enum Pass<C: Cost> {
+    Found(C, Vec<Point>),
+    /// Smallest f that went over the bound, `C::INFINITY` if none did.
+    Exceeded(C),
+    Stopped,
+}
+
+/// Read-only state for a pass.
+struct Probe<'a, C: Cost> {
+    graph: &'a [Vec<char>],
+    goal: Node<C>,
+    heur: &'a dyn Heuristic,
+    budget: &'a Budget,
+    // Raised when another thread finds the goal, so the rest can give up.
+    found: &'a AtomicBool,
+}
+
+/// Bounded DFS below the last node of `prefix`, which is reached with cost
+/// `root.g`. Without a table, cycles are cut by checking the current path.
+fn bounded_dfs<C: Cost>(prefix: &[Point], root: Node<C>, bound: C, probe: &Probe<C>, mut table: Option<&mut Transpositions<C>>,
+                        stats: &mut SearchStats) -> Pass<C> {
+    let adjacent = [(0, 1), (-1, 0), (1, 0), (0, -1)];
+    let mut path = prefix.to_vec();
+    let mut on_path: HashSet<Point> = prefix.iter().copied().collect();
+    let mut stack: Vec<(Node<C>, usize)> = vec![(root, 0)];
+    let mut next_bound = C::INFINITY;
+
+    while let Some((node, next)) = stack.last_mut() {
+        let node = *node;
+
+        if *next == adjacent.len() {
+            stack.pop();
+            on_path.remove(&node.position);
+            path.pop();
+            continue;
+        }
+
+        // The thread's expansions carry over between subtrees and iterations,
+        // so the limits are polled on schedule however small the subtrees are.
+        if *next == 0 {
+            stats.expansions += 1;
+            probe.budget.expanded();
+
+            if stats.expansions.is_multiple_of(CHECK_INTERVAL) && (probe.budget.exceeded() || probe.found.load(Ordering::SeqCst)) {
+                return Pass::Stopped;
+            }
+        }
+
+        let (x, y) = adjacent[*next];
+        *next += 1;
+
+        if !helpers::is_valid_neighbor(probe.graph, &node, x, y) {
+            continue;
+        }
+
+        let mut n_prime = Node::new(node.position.x + x, node.position.y + y, C::ZERO, node.g + C::from_u32(1), C::ZERO, node.position);
+
+        if table.is_none() && on_path.contains(&n_prime.position) {
+            stats.duplicates += 1;
+            continue;
+        }
+
+        stats.generated += 1;
+        n_prime.h = helpers::heuristic(n_prime, probe.goal, probe.heur);
+        stats.evaluations += 1;
+        n_prime.f = n_prime.g + n_prime.h;
+
+        if n_prime.f > bound {
+            next_bound = cmp::min(next_bound, n_prime.f);
+            continue;
+        }
+
+        if let Some(table) = table.as_mut() {
+            if table.seen(n_prime.position, n_prime.g, probe.budget) {
+                stats.duplicates += 1;
+                continue;
+            }
+        }
+
+        path.push(n_prime.position);
+
+        if n_prime == probe.goal {
+            return Pass::Found(n_prime.g, path);
+        }
+
+        on_path.insert(n_prime.position);
+        stack.push((n_prime, 0));
+    }
+
+    Pass::Exceeded(next_bound)
+}
+
+/// Splits the tree below the start into independent subtrees, breadth first,
+/// until there are enough to keep every thread busy. Returns each subtree's
+/// path from the start along with the smallest f cut off while splitting,
+/// or the result outright if the tree is exhausted or the goal turns up.
+fn split<C: Cost>(start: Node<C>, bound: C, threads: usize, probe: &Probe<C>, stats: &mut SearchStats) -> Result<(Vec<Subtree<C>>, C), Pass<C>> {
+    let adjacent = [(0, 1), (-1, 0), (1, 0), (0, -1)];
+    let mut frontier = vec![(vec![start.position], start)];
+    let mut next_bound = C::INFINITY;
+
+    while frontier.len() < threads * SUBTREES_PER_THREAD {
+        let mut deeper = Vec::with_capacity(frontier.len() * 3);
+
+        for (path, node) in &frontier {
+            stats.expansions += 1;
+            probe.budget.expanded();
+
+            for (x, y) in adjacent.iter() {
+                if !helpers::is_valid_neighbor(probe.graph, node, *x, *y) {
+                    continue;
+                }
+
+                let mut n_prime = Node::new(node.position.x + x, node.position.y + y, C::ZERO, node.g + C::from_u32(1), C::ZERO, node.position);
+
+                if path.contains(&n_prime.position) {
+                    stats.duplicates += 1;
+                    continue;
+                }
+
+                stats.generated += 1;
+                n_prime.h = helpers::heuristic(n_prime, probe.goal, probe.heur);
+                stats.evaluations += 1;
+                n_prime.f = n_prime.g + n_prime.h;
+
+                if n_prime.f > bound {
+                    next_bound = cmp::min(next_bound, n_prime.f);
+                    continue;
+                }
+
+                let mut extended = path.clone();
+                extended.push(n_prime.position);
+
+                if n_prime == probe.goal {
+                    return Err(Pass::Found(n_prime.g, extended));
+                }
+
+                deeper.push((extended, n_prime));
+            }
+        }
+
+        if deeper.is_empty() {
+            return Err(Pass::Exceeded(next_bound));
+        }
+
+        frontier = deeper;
+    }
+
+    Ok((frontier, next_bound))
+}
+
+/// One iteration with the subtrees below the start shared between threads.
+/// Every solution found within a bound that the previous iteration proved
+/// empty costs exactly the bound, so whichever thread gets there first wins.
+/// The splitting is counted in the first thread's `stats`.
+fn parallel_pass<C: Cost>(start: Node<C>, bound: C, probe: &Probe<C>, tables: &mut [Option<Transpositions<C>>],
+                          stats: &mut [SearchStats]) -> Pass<C> {
+    let (subtrees, cut_off) = match split(start, bound, tables.len(), probe, &mut stats[0]) {
+        Ok(split) => split,
+        Err(pass) => return pass,
+    };
+
+    let queue = Mutex::new(subtrees);
+    let found: Mutex<Option<(C, Vec<Point>)>> = Mutex::new(None);
+    let next_bound = Mutex::new(cut_off);
+    let stopped = AtomicBool::new(false);
+    let thread_probe = Probe { found: &stopped, ..*probe };
+
+    thread::scope(|scope| {
+        for (table, stats) in tables.iter_mut().zip(stats.iter_mut()) {
+            let (queue, found, next_bound, probe) = (&queue, &found, &next_bound, &thread_probe);
+
+            scope.spawn(move || {
+                loop {
+                    let subtree = queue.lock().unwrap().pop();
+                    let (prefix, root) = match subtree {
+                        Some(subtree) => subtree,
+                        None => return,
+                    };
+
+                    match bounded_dfs(&prefix, root, bound, probe, table.as_mut(), stats) {
+                        Pass::Found(cost, path) => {
+                            found.lock().unwrap().get_or_insert((cost, path));
+                            probe.found.store(true, Ordering::SeqCst);
+                            return;
+                        },
+                        Pass::Exceeded(f) => {
+                            let mut next = next_bound.lock().unwrap();
+                            *next = cmp::min(*next, f);
+                        },
+                        Pass::Stopped => return,
+                    }
+                }
+            });
+        }
+    });
+
+    if let Some((cost, path)) = found.into_inner().unwrap() {
+        return Pass::Found(cost, path);
+    }
+
+    if probe.budget.exceeded() {
+        return Pass::Stopped;
+    }
+
+    Pass::Exceeded(next_bound.into_inner().unwrap())
+}
+
+/// Iterative-deepening A*. Memory use is the current path plus, when
+/// `Flags::table_size` is set, a fixed-size transposition table per thread,
+/// which grid maps need to avoid re-searching the same cells over and over.
+/// With more than one thread every iteration is split into subtrees that
+/// the threads search independently. Each thread's statistics add up every
+/// iteration, and include how many entries its table holds at the end.
+pub fn setup<C: Cost>(start_point: Point, end_point: Point, flags: Flags) -> Solution<C> {
+    let Flags { heur, graph, threads: thread_cnt, limits, table_size, .. } = flags;
+    let thread_cnt = cmp::max(thread_cnt, 1);
+    let budget = Budget::new(limits);
+    let never = AtomicBool::new(false);
+    let probe = Probe {
+        graph: &graph,
+        goal: Node::at(end_point),
+        heur: &*heur,
+        budget: &budget,
+        found: &never,
+    };
+
+    let mut start = Node::at(start_point);
+    start.h = helpers::heuristic(start, probe.goal, &*heur);
+    start.f = start.g + start.h;
+
+    if start == probe.goal {
+        return Solution::new(C::ZERO, vec![start.position], budget.reason());
+    }
+
+    let mut tables: Vec<Option<Transpositions<C>>> = (0..thread_cnt)
+        .map(|_| table_size.map(|size| Transpositions::new(size / thread_cnt)))
+        .collect();
+    let mut stats = vec![SearchStats::default(); thread_cnt];
+    let mut bound = start.f;
+
+    loop {
+        for table in tables.iter_mut().flatten() {
+            table.next_iteration();
+        }
+
+        let pass = if thread_cnt == 1 {
+            bounded_dfs(&[start.position], start, bound, &probe, tables[0].as_mut(), &mut stats[0])
+        }
+        else {
+            parallel_pass(start, bound, &probe, &mut tables, &mut stats)
+        };
+
+        let (cost, path) = match pass {
+            Pass::Found(cost, path) => (cost, path),
+            Pass::Exceeded(next) if next == C::INFINITY => (C::INFINITY, Vec::new()),
+            Pass::Stopped => (C::INFINITY, Vec::new()),
+            Pass::Exceeded(next) => {
+                bound = next;
+                continue;
+            },
+        };
+
+        for (stats, table) in stats.iter_mut().zip(&tables) {
+            stats.table_entries = table.as_ref().map_or(0, |table| table.occupied);
+        }
+
+        return Solution::new(cost, path, budget.reason()).with_stats(stats);
+    }
+}
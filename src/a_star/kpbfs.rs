@@ -1,149 +1,251 @@
+use ::atomic::Atomic;
 use std::{
-    thread,
     mem::drop,
-    collections::{HashMap, BinaryHeap},
-    sync::{Arc, Mutex},
-    sync::atomic::{AtomicBool, Ordering}
+    collections::HashMap,
+    sync::{Arc, Condvar, Mutex},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering}
 };
 use super::utils::{
-	structs::{Node, Point, Flags},
+    pool::Workers,
+	structs::{Incumbent, Node, Point, Flags, Solution},
+    cost::Cost,
+    open_list::OpenList,
+    limits::Budget,
+    stats::SearchStats,
     helpers
 };
 
 // Best performance seen with high threading, threads > cores
 
-fn search(
-    _start: Node,
+/// Every node generated so far at its best known cost, and whether that copy
+/// has been expanded.
+type ClosedList<C> = HashMap<Point, (Node<C>, bool)>;
+
+#[allow(clippy::too_many_arguments)]
+fn search<C: Cost>(
+    _start: Node<C>,
     _id: usize,
-    goal_node: Node,
-    open: Arc<Mutex<BinaryHeap<Node>>>,
-    closed_list: Arc<Mutex<HashMap<Point, Node>>>,
+    goal_node: Node<C>,
+    open: Arc<Mutex<OpenList<C>>>,
+    // Signalled under the open list's lock whenever a thread finishes an
+    // expansion, which is the only time work can appear or the search end.
+    idle: Arc<Condvar>,
+    closed_list: Arc<Mutex<ClosedList<C>>>,
+    incumbent: Arc<Atomic<Incumbent<C>>>,
+    expanding: Arc<AtomicUsize>,
+    budget: Arc<Budget>,
     finished: &AtomicBool,
-    graph: Vec<Vec<char>>,
     flags: Flags,
-) {
+) -> SearchStats {
+    let mut stats = SearchStats::default();
+
     loop {
         if finished.load(Ordering::SeqCst) {
-            return;
+            return stats;
+        }
+
+        if budget.exceeded() {
+            finished.swap(true, Ordering::SeqCst);
+            return stats;
         }
+
         // wait for open to have node and try getting node
         let mut pq = open.lock().unwrap();
+        let best = incumbent.load(Ordering::SeqCst).cost;
+
+        // Anytime runs prune on g + h as nodes are popped, which the bound cannot
+        // tell, so they only stop once open runs dry.
+        let exhausted = match flags.anytime {
+            Some(_) => pq.is_empty(),
+            None => pq.bound().is_none_or(|bound| bound >= best),
+        };
+
+        // Nothing left can improve the incumbent. Only threads mid-expansion push
+        // to open, so once none are left the search is over.
+        if exhausted {
+            if expanding.load(Ordering::SeqCst) == 0 {
+                finished.swap(true, Ordering::SeqCst);
+                drop(pq);
+                idle.notify_all();
+                return stats;
+            }
 
-        if pq.len() == 0 {
+            drop(idle.wait(pq).unwrap());
             continue;
         }
 
         let node = pq.pop().unwrap();
+        // Counted while the lock is held so the check above never misses it.
+        expanding.fetch_add(1, Ordering::SeqCst);
         drop(pq);
 
-        //println!("{},{} {}", node.position.x, node.position.y, id);
+        budget.expanded();
+        expand(node, goal_node, &open, &closed_list, &incumbent, &budget, &flags.graph, &flags, &mut stats);
 
-        // If this is equal to the goal node
-        if node.position.x == goal_node.position.x && node.position.y == goal_node.position.y
-        {
-            println!("found goal! ({},{}).g cost={}", node.position.x, node.position.y, node.g);
-            //  Store this and notfiy other threads
-            finished.swap(true, Ordering::SeqCst);
-            return;
-        }
+        // Taken under the lock, so a thread about to wait cannot miss it.
+        let pq = open.lock().unwrap();
+        expanding.fetch_sub(1, Ordering::SeqCst);
+        drop(pq);
+        idle.notify_all();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn expand<C: Cost>(
+    node: Node<C>,
+    goal_node: Node<C>,
+    open: &Mutex<OpenList<C>>,
+    closed_list: &Mutex<ClosedList<C>>,
+    incumbent: &Atomic<Incumbent<C>>,
+    budget: &Budget,
+    graph: &[Vec<char>],
+    flags: &Flags,
+    stats: &mut SearchStats,
+) {
+    if flags.anytime.is_some() && node.g + node.h >= incumbent.load(Ordering::SeqCst).cost {
+        return;
+    }
 
-        // Check the closed list
-        let mut cl = closed_list.lock().unwrap();
-        if cl.contains_key(&node.position) {
-            if cl.get(&node.position).unwrap().g < node.g {
-                continue;
+    // If this is equal to the goal node, store it as the incumbent if it improves on it.
+    if node.position.x == goal_node.position.x && node.position.y == goal_node.position.y
+    {
+        stats.expansions += 1;
+
+        let mut current = incumbent.load(Ordering::SeqCst);
+
+        while node.g < current.cost {
+            match incumbent.compare_exchange(current, Incumbent::new(node, node.g), Ordering::SeqCst, Ordering::SeqCst) {
+                Ok(_) => {
+                    if let Some(anytime) = &flags.anytime {
+                        anytime.report(node.g, trace(&closed_list.lock().unwrap(), node.position, graph));
+                    }
+                    break;
+                },
+                Err(actual) => current = actual,
             }
         }
-        cl.insert(node.position, node);
-        // Release the lock.
-        drop(cl);
+        return;
+    }
 
-        //println!("{},{} g={}", node.position.x, node.position.y, node.g);
+    // Check the closed list
+    let mut cl = closed_list.lock().unwrap();
+    if cl.contains_key(&node.position) && cl.get(&node.position).unwrap().0.g < node.g {
+        return;
+    }
+    cl.insert(node.position, (node, true));
+    // Release the lock.
+    drop(cl);
 
-        let adjacent = vec![(0, 1), (-1, 0), (1, 0), (0, -1)];
+    stats.expansions += 1;
 
-        for (x, y) in adjacent {
-            let n_x = node.position.x + x;
-            let n_y = node.position.y + y;
-            if n_x < 0 || n_y < 0 {
-                continue;
-            }
+    let adjacent = vec![(0, 1), (-1, 0), (1, 0), (0, -1)];
 
-            if helpers::is_valid_neighbor(&graph, &node, x, y) {
-            //if is_valid(n_x as usize, n_y as usize, &graph) {
-                // x: i32, y: i32, f: i128, g: i128, h: i128, parent: Point
-                let mut n_prime = Node::new(n_x, n_y, 0, node.g + 1, 0, node.position);
-                n_prime.h = helpers::heuristic(n_prime, goal_node, &flags.heur);
-                n_prime.f = n_prime.g + n_prime.h;
-
-                // check if closed list contains it
-                let mut prime_cl = closed_list.lock().unwrap();
-                if prime_cl.contains_key(&n_prime.position) {
-                    if prime_cl.get(&n_prime.position).unwrap().g <= n_prime.g {
-                        continue;
-                    }
-                }
-                prime_cl.insert(n_prime.position, n_prime);
-                // Release the lock.
-                drop(prime_cl);
-
-                // add to pq
-                let mut add_pq = open.lock().unwrap();
-                add_pq.push(n_prime);
-                drop(add_pq);
-                // add_pq goes out of scope here.
+    for (x, y) in adjacent {
+        let n_x = node.position.x + x;
+        let n_y = node.position.y + y;
+        if n_x < 0 || n_y < 0 {
+            continue;
+        }
+
+        if helpers::is_valid_neighbor(graph, &node, x, y) {
+            // x: i32, y: i32, f: i128, g: i128, h: i128, parent: Point
+            let mut n_prime = Node::new(n_x, n_y, C::ZERO, node.g + C::from_u32(1), C::ZERO, node.position);
+            n_prime.h = helpers::heuristic(n_prime, goal_node, &*flags.heur);
+            n_prime.f = flags.mode.f(n_prime.g, n_prime.h);
+            stats.generated += 1;
+            stats.evaluations += 1;
+
+            // check if closed list contains it
+            let mut prime_cl = closed_list.lock().unwrap();
+            match prime_cl.get(&n_prime.position) {
+                Some((known, _)) if known.g <= n_prime.g => {
+                    stats.duplicates += 1;
+                    continue;
+                },
+                Some((_, true)) => stats.reopened += 1,
+                _ => (),
             }
+            prime_cl.insert(n_prime.position, (n_prime, false));
+            // Release the lock.
+            drop(prime_cl);
+
+            // add to pq
+            budget.stored();
+            let mut add_pq = open.lock().unwrap();
+            add_pq.push(n_prime);
+            drop(add_pq);
+            // add_pq goes out of scope here.
         }
     }
 }
 
-pub fn setup(start_point: Point, end_point: Point, flags: Flags) {
-    let Flags { heur, graph, threads: thread_cnt } = flags;
+/// Path to `end` through the shared closed list.
+fn trace<C: Cost>(closed_list: &ClosedList<C>, end: Point, graph: &[Vec<char>]) -> Vec<Point> {
+    helpers::trace_path(end, graph.len() * graph.len(), |point| closed_list.get(&point).map(|(node, _)| node.parent))
+}
+
+pub fn setup<C: Cost>(start_point: Point, end_point: Point, flags: Flags) -> Solution<C> {
+    let Flags { heur, graph, threads: thread_cnt, mode, anytime, limits, tie_break, pinning, pool, .. } = flags;
+    let workers = Workers::new(pool.as_deref(), pinning, thread_cnt);
     let mut threads = Vec::with_capacity(thread_cnt);
 
     // KPBFS uses global open and close lists
-    let open: Arc<Mutex<BinaryHeap<Node>>> = Arc::new(Mutex::new(BinaryHeap::new()));
-    let closed_list: Arc<Mutex<HashMap<Point, Node>>> = Arc::new(Mutex::new(HashMap::new()));
+    let open: Arc<Mutex<OpenList<C>>> = Arc::new(Mutex::new(OpenList::new(mode, tie_break)));
+    let idle: Arc<Condvar> = Arc::new(Condvar::new());
+    let closed_list: Arc<Mutex<ClosedList<C>>> = Arc::new(Mutex::new(ClosedList::new()));
 
     let finished: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+    let expanding: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+    let budget: Arc<Budget> = Arc::new(Budget::new(limits.clone()));
 
-    let mut start = Node::new(start_point.x, start_point.y, 0, 0, 0, Point::default());
-    let end = Node::new(end_point.x, end_point.y, 0, 0, 0, Point::default());
-    start.h = helpers::heuristic(start, end, &flags.heur);
-    start.f = start.g + start.h;
+    let mut start = Node::at(start_point);
+    let end = Node::at(end_point);
+    start.h = helpers::heuristic(start, end, &*heur);
+    start.f = mode.f(start.g, start.h);
+    let incumbent: Arc<Atomic<Incumbent<C>>> = Arc::new(Atomic::new(Incumbent::new(start, C::INFINITY)));
 
     // Add to open
     let mut init_open = open.lock().unwrap();
     let mut init_cl = closed_list.lock().unwrap();
     init_open.push(start);
-    init_cl.insert(start.position, start);
+    init_cl.insert(start.position, (start, false));
     drop(init_open);
     drop(init_cl);
 
     for i in 0..thread_cnt {
         let clone_open = Arc::clone(&open);
+        let clone_idle = Arc::clone(&idle);
         let clone_closed_list = Arc::clone(&closed_list);
+        let clone_incumbent = Arc::clone(&incumbent);
+        let clone_expanding = Arc::clone(&expanding);
+        let clone_budget = Arc::clone(&budget);
         let clone_fin = Arc::clone(&finished);
-        let graph = graph.clone();
-        let flags = Flags { graph: graph.clone(), heur, threads: thread_cnt };
+        let flags = Flags { graph: graph.clone(), heur: heur.clone(), threads: thread_cnt, mode, anytime: anytime.clone(), limits: limits.clone(),
+                            tie_break, ..Flags::default() };
 
         // Here we'd pass a start node to each thread.
-        threads.push(thread::spawn(move || {
+        threads.push(workers.spawn(i, move || {
             search(
                 start,
                 i,
                 end,
                 clone_open,
+                clone_idle,
                 clone_closed_list,
+                clone_incumbent,
+                clone_expanding,
+                clone_budget,
                 &clone_fin,
-                graph,
                 flags,
-            );
+            )
         }))
     }
 
     // Final answer is outputted once all threads are done.
-    for thread in threads {
-        thread.join().expect("Panic");
-    }
+    let (placement, stats) = threads.into_iter().map(|thread| thread.join().expect("Panic")).unzip();
+
+    let final_incumbent = incumbent.load(Ordering::SeqCst);
+    let path = trace(&closed_list.lock().unwrap(), final_incumbent.node.position, &graph);
+
+    Solution::new(final_incumbent.cost, path, budget.reason()).with_stats(stats).with_placement(placement)
 }
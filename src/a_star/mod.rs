@@ -1,4 +1,11 @@
+pub mod astar;
+pub mod bidir;
 pub mod dpa;
 pub mod hda;
+pub mod hpa;
+pub mod ida;
 pub mod kpbfs;
-pub mod utils;
\ No newline at end of file
+pub mod solver;
+pub mod theta;
+pub mod utils;
+pub mod wsbfs;
\ No newline at end of file
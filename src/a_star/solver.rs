@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use super::{
+    utils::{
+        structs::{Point, Flags, Solution},
+        cost::Cost,
+        limits::Limits,
+        pool::Pool
+    },
+    astar,
+    hda,
+    hpa::{self, HpaIndex},
+    dpa,
+    kpbfs,
+    wsbfs,
+    bidir,
+    ida,
+    theta
+};
+
+/// The searches a `Solver` can run.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Algorithm {
+    Astar,
+    Hda,
+    Hpa,
+    Dpa,
+    Kpbfs,
+    Wsbfs,
+    Bidir,
+    Ida,
+    Theta,
+    LazyTheta,
+}
+
+/// Answers any number of queries on one map with one algorithm and set of
+/// flags. The map is shared with every query instead of copied, the worker
+/// threads are started once and kept in a `Pool`, along with the cell table
+/// of "hda" and "dpa", and "hpa" builds its index once. Queries that run on
+/// the pool take turns, so a solver shared between threads is safe to use.
+pub struct Solver {
+    algo: Algorithm,
+    flags: Flags,
+    index: Option<HpaIndex>,
+}
+
+impl Solver {
+    /// Starts `flags.threads` workers, pinned as `flags.pinning` says.
+    pub fn new(algo: Algorithm, flags: Flags) -> Solver {
+        let index = if algo == Algorithm::Hpa { Some(HpaIndex::new(flags.graph.clone(), hpa::DEFAULT_CLUSTER_SIZE, flags.threads)) } else { None };
+        let pool = Arc::new(Pool::new(flags.threads, flags.pinning));
+
+        Solver { algo, flags: Flags { pool: Some(pool), ..flags }, index }
+    }
+
+    /// Searches from `start` to `end` within `limits`, which replace the
+    /// solver's own for this query.
+    pub fn solve<C: Cost>(&self, start: Point, end: Point, limits: Limits) -> Solution<C> {
+        let flags = Flags { limits, ..self.flags.clone() };
+
+        if let Some(index) = &self.index {
+            return index.search(start, end, &*flags.heur, flags.limits);
+        }
+
+        match self.algo {
+            Algorithm::Astar => astar::setup(start, end, flags),
+            Algorithm::Hda => hda::setup(start, end, flags),
+            Algorithm::Hpa => hpa::setup(start, end, flags),
+            Algorithm::Dpa => dpa::setup(start, end, flags),
+            Algorithm::Kpbfs => kpbfs::setup(start, end, flags),
+            Algorithm::Wsbfs => wsbfs::setup(start, end, flags),
+            Algorithm::Bidir => bidir::setup(start, end, flags),
+            Algorithm::Ida => ida::setup(start, end, flags),
+            Algorithm::Theta => theta::setup(start, end, flags),
+            Algorithm::LazyTheta => theta::setup_lazy(start, end, flags),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet}
+};
+use super::utils::{
+    structs::{Node, Point, Flags, Solution},
+    cost::Cost,
+    limits::Budget,
+    helpers
+};
+
+/// Open list entry. Any-angle costs are real valued, so the search works in
+/// `f64` whatever the cost type and only converts the final length.
+#[derive(Clone, Copy)]
+struct Entry {
+    f: f64,
+    g: f64,
+    position: Point,
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.f.total_cmp(&self.f)
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+fn distance(from: Point, to: Point) -> f64 {
+    helpers::path_length(&[from, to])
+}
+
+/// Theta*: A* over the four-connected grid where a successor takes its
+/// parent's parent whenever the two can see each other, so paths bend only
+/// at obstacle corners.
+pub fn setup<C: Cost>(start_point: Point, end_point: Point, flags: Flags) -> Solution<C> {
+    search(start_point, end_point, flags, false)
+}
+
+/// Lazy Theta*: assumes every line of sight holds when a node is generated
+/// and only checks it when the node is expanded, falling back to the best
+/// expanded neighbor if it does not. Far fewer line-of-sight checks than
+/// Theta*, for paths that are usually the same length.
+pub fn setup_lazy<C: Cost>(start_point: Point, end_point: Point, flags: Flags) -> Solution<C> {
+    search(start_point, end_point, flags, true)
+}
+
+/// Single-threaded, honouring only the heuristic and limits. The path is the
+/// list of turning points and the cost its Euclidean length, rounded to the
+/// nearest whole for integer costs; `helpers::path_length` gives the exact
+/// length either way. Manhattan distance and landmarks measure four-connected
+/// paths, which overestimates any-angle ones, so they lose the length
+/// guarantee.
+fn search<C: Cost>(start_point: Point, end_point: Point, flags: Flags, lazy: bool) -> Solution<C> {
+    let Flags { heur, graph, limits, .. } = flags;
+    let adjacent = [(0, 1), (-1, 0), (1, 0), (0, -1)];
+    let budget = Budget::new(limits);
+    let estimate = |point: Point| heur.estimate(point, end_point);
+    let mut open = BinaryHeap::new();
+    let mut closed: HashSet<Point> = HashSet::new();
+    // Best g found so far for every generated cell, and the parent it came from.
+    let mut best: HashMap<Point, (f64, Point)> = HashMap::new();
+
+    best.insert(start_point, (0.0, Point::default()));
+    open.push(Entry { f: estimate(start_point), g: 0.0, position: start_point });
+
+    while let Some(Entry { g, position, .. }) = open.pop() {
+        if budget.exceeded() {
+            break;
+        }
+
+        if closed.contains(&position) || best[&position].0 < g {
+            continue;
+        }
+
+        let (mut g, mut parent) = best[&position];
+
+        // The line of sight assumed when this was generated has to hold now.
+        if lazy && parent != Point::default() && !helpers::line_of_sight(&graph, parent, position) {
+            let fallback = adjacent.iter()
+                .map(|(x, y)| Point { x: position.x + x, y: position.y + y })
+                .filter(|neighbor| closed.contains(neighbor))
+                .map(|neighbor| (best[&neighbor].0 + 1.0, neighbor))
+                .min_by(|a, b| a.0.total_cmp(&b.0));
+
+            // A node is only generated from an expanded neighbor, so there is one.
+            if let Some(fallback) = fallback {
+                (g, parent) = fallback;
+                best.insert(position, (g, parent));
+            }
+        }
+
+        budget.expanded();
+        closed.insert(position);
+
+        if position == end_point {
+            let path = helpers::trace_path(position, graph.len() * graph.len(), |point| best.get(&point).map(|(_, parent)| *parent));
+
+            return Solution::new(C::from_f64(g), path, budget.reason());
+        }
+
+        let node = Node::<C>::at(position);
+
+        for (x, y) in adjacent.iter() {
+            if !helpers::is_valid_neighbor(&graph, &node, *x, *y) {
+                continue;
+            }
+
+            let successor = Point { x: position.x + x, y: position.y + y };
+
+            if closed.contains(&successor) {
+                continue;
+            }
+
+            // Path 2 goes straight from the parent, path 1 through this node.
+            let (successor_g, successor_parent) = if parent != Point::default()
+                && (lazy || helpers::line_of_sight(&graph, parent, successor)) {
+                (best[&parent].0 + distance(parent, successor), parent)
+            }
+            else {
+                (g + 1.0, position)
+            };
+
+            if best.get(&successor).is_some_and(|(known, _)| *known <= successor_g) {
+                continue;
+            }
+
+            best.insert(successor, (successor_g, successor_parent));
+            budget.stored();
+            open.push(Entry { f: successor_g + estimate(successor), g: successor_g, position: successor });
+        }
+    }
+
+    Solution::new(C::INFINITY, Vec::new(), budget.reason())
+}
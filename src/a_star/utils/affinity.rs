@@ -0,0 +1,136 @@
+use std::{
+    fs,
+    thread::{self, JoinHandle}
+};
+
+/// How workers are spread over the CPUs the process may run on.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Pinning {
+    /// Worker i on the i-th allowed CPU, in the order the OS numbers them.
+    RoundRobin,
+    /// Fills one socket before the next and a core's hyperthreads before the
+    /// next core, so workers share caches and memory.
+    Compact,
+    /// Alternates between sockets and uses every core before any second
+    /// hyperthread, so workers get the most cache and memory bandwidth each.
+    Scatter,
+}
+
+/// Where a CPU sits in the machine, as Linux reports it.
+#[derive(Copy, Clone, Debug)]
+struct Cpu {
+    id: usize,
+    socket: usize,
+    core: usize,
+    // Which of its core's hyperthreads it is, 0 for the first.
+    sibling: usize,
+}
+
+impl Pinning {
+    /// The CPU for each of `threads` workers, wrapping around when there are
+    /// more workers than CPUs. Empty if the CPUs cannot be found out, as on
+    /// systems other than Linux.
+    pub fn plan(&self, threads: usize) -> Vec<usize> {
+        let mut cpus = topology();
+
+        if cpus.is_empty() {
+            return Vec::new();
+        }
+
+        match self {
+            Pinning::RoundRobin => (),
+            Pinning::Compact => cpus.sort_by_key(|cpu| (cpu.socket, cpu.core, cpu.sibling, cpu.id)),
+            Pinning::Scatter => {
+                cpus.sort_by_key(|cpu| (cpu.sibling, cpu.core, cpu.socket, cpu.id));
+
+                // Each socket's CPUs in that order, then one from each in turn.
+                let mut sockets: Vec<Vec<Cpu>> = Vec::new();
+                for cpu in cpus.drain(..) {
+                    match sockets.iter_mut().find(|socket| socket[0].socket == cpu.socket) {
+                        Some(socket) => socket.push(cpu),
+                        None => sockets.push(vec![cpu]),
+                    }
+                }
+                sockets.sort_by_key(|socket| socket[0].socket);
+
+                let longest = sockets.iter().map(Vec::len).max().unwrap_or(0);
+                cpus = (0..longest).flat_map(|i| sockets.iter().filter_map(move |socket| socket.get(i).copied())).collect();
+            },
+        }
+
+        (0..threads).map(|i| cpus[i % cpus.len()].id).collect()
+    }
+}
+
+/// CPUs this process may run on, by id.
+fn topology() -> Vec<Cpu> {
+    let mut cpus: Vec<Cpu> = allowed().into_iter()
+        .map(|id| Cpu { id, socket: read_topology(id, "physical_package_id"), core: read_topology(id, "core_id"), sibling: 0 })
+        .collect();
+
+    for i in 0..cpus.len() {
+        cpus[i].sibling = cpus[..i].iter().filter(|cpu| cpu.socket == cpus[i].socket && cpu.core == cpus[i].core).count();
+    }
+
+    cpus
+}
+
+fn read_topology(cpu: usize, field: &str) -> usize {
+    fs::read_to_string(format!("/sys/devices/system/cpu/cpu{}/topology/{}", cpu, field))
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(target_os = "linux")]
+fn allowed() -> Vec<usize> {
+    use std::mem;
+
+    unsafe {
+        let mut set: libc::cpu_set_t = mem::zeroed();
+
+        if libc::sched_getaffinity(0, mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return Vec::new();
+        }
+
+        (0..libc::CPU_SETSIZE as usize).filter(|cpu| libc::CPU_ISSET(*cpu, &set)).collect()
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn allowed() -> Vec<usize> {
+    Vec::new()
+}
+
+/// Pins the calling thread to `cpu`. False if it could not be.
+#[cfg(target_os = "linux")]
+pub fn pin(cpu: usize) -> bool {
+    use std::mem;
+
+    unsafe {
+        let mut set: libc::cpu_set_t = mem::zeroed();
+
+        libc::CPU_SET(cpu, &mut set);
+        libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &set) == 0
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pin(_cpu: usize) -> bool {
+    false
+}
+
+/// Spawns worker `i` of a search, pinned to its CPU in `plan` if there is
+/// one. It pins itself before running `work`, so whatever `work` allocates
+/// is first touched from that CPU and lands in its socket's memory. Gives
+/// back the CPU the worker actually ran pinned to along with its result.
+pub fn spawn<F, T>(plan: &[usize], i: usize, work: F) -> JoinHandle<(Option<usize>, T)>
+    where F: FnOnce() -> T + Send + 'static, T: Send + 'static {
+    let cpu = plan.get(i).copied();
+
+    thread::spawn(move || {
+        let cpu = cpu.filter(|cpu| pin(*cpu));
+
+        (cpu, work())
+    })
+}
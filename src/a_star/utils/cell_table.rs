@@ -0,0 +1,141 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    sync::atomic::{AtomicU32, AtomicU64, Ordering}
+};
+use super::{structs::Point, cost::Cost};
+
+/// Best g a thread has for a cell, and whether that copy has been expanded.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Cell<C: Cost> {
+    pub g: C,
+    pub closed: bool,
+}
+
+/// One thread's open and closed bookkeeping: the best g it has been sent
+/// for each cell it owns.
+pub enum CellTable<C: Cost> {
+    /// A view of a table shared by all threads of the search. Each cell is
+    /// only ever written by its owner, so threads never touch the same entry.
+    /// Cells whose g is too large for an entry are kept in the map instead.
+    Grid(Arc<GridTable>, HashMap<Point, Cell<C>>),
+    /// For maps too large to index, keyed by point.
+    Hashed(HashMap<Point, Cell<C>>),
+}
+
+impl<C: Cost> CellTable<C> {
+    /// A view of `grid` if there is one, otherwise a table of the thread's own.
+    pub fn new(grid: Option<Arc<GridTable>>) -> CellTable<C> {
+        match grid {
+            Some(grid) => CellTable::Grid(grid, HashMap::new()),
+            None => CellTable::Hashed(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, point: Point) -> Option<Cell<C>> {
+        match self {
+            CellTable::Grid(grid, overflow) => match grid.get(point) {
+                Some(Slot::Held(cell)) => Some(cell),
+                Some(Slot::Overflowed) => overflow.get(&point).copied(),
+                None => None,
+            },
+            CellTable::Hashed(cells) => cells.get(&point).copied(),
+        }
+    }
+
+    pub fn set(&mut self, point: Point, cell: Cell<C>) {
+        match self {
+            CellTable::Grid(grid, overflow) => {
+                if !grid.set(point, cell) {
+                    overflow.insert(point, cell);
+                }
+            },
+            CellTable::Hashed(cells) => {
+                cells.insert(point, cell);
+            },
+        }
+    }
+}
+
+// An entry packs the generation that wrote it above the closed flag and g.
+const CLOSED: u64 = 1 << 32;
+// g of an entry whose real g did not fit, which is kept by its owner instead.
+const OVERFLOWED: u32 = u32::MAX;
+const GENERATION_SHIFT: u32 = 33;
+const MAX_GENERATION: u32 = 1 << (64 - GENERATION_SHIFT);
+
+/// What a `GridTable` entry of the current generation holds.
+pub enum Slot<C: Cost> {
+    Held(Cell<C>),
+    /// The cell was written with a g too large for an entry.
+    Overflowed,
+}
+
+/// A flat array with an entry per cell of a square grid. Entries are stamped
+/// with the generation that wrote them and anything from an older one reads
+/// as empty, so the table is cleared for another search in constant time.
+pub struct GridTable {
+    side: usize,
+    entries: Vec<AtomicU64>,
+    generation: AtomicU32,
+}
+
+impl GridTable {
+    /// A table for `graph` with costs of type `C`, or `None` if its cells
+    /// cannot all be indexed or costs are not whole numbers. The odd g too
+    /// large for an entry is handed back by `set` rather than refused here.
+    pub fn for_graph<C: Cost>(graph: &[Vec<char>]) -> Option<GridTable> {
+        let cells = graph.len().checked_mul(graph.len())?;
+
+        if !C::INTEGER || cells == 0 || cells > u32::MAX as usize / 2 {
+            return None;
+        }
+
+        Some(GridTable { side: graph.len(), entries: (0..cells).map(|_| AtomicU64::new(0)).collect(), generation: AtomicU32::new(1) })
+    }
+
+    /// Whether this is a table for `graph`'s size of map.
+    pub fn fits(&self, graph: &[Vec<char>]) -> bool {
+        self.side == graph.len()
+    }
+
+    /// Forgets every entry, for use by another search. Clears them for real
+    /// only once the generation would no longer fit in an entry.
+    pub fn reset(&self) {
+        if self.generation.fetch_add(1, Ordering::SeqCst) + 1 == MAX_GENERATION {
+            for entry in self.entries.iter() {
+                entry.store(0, Ordering::Relaxed);
+            }
+            self.generation.store(1, Ordering::SeqCst);
+        }
+    }
+
+    fn index(&self, point: Point) -> usize {
+        point.x as usize * self.side + point.y as usize
+    }
+
+    pub fn get<C: Cost>(&self, point: Point) -> Option<Slot<C>> {
+        let entry = self.entries[self.index(point)].load(Ordering::Relaxed);
+
+        if entry >> GENERATION_SHIFT != self.generation.load(Ordering::Relaxed) as u64 {
+            return None;
+        }
+
+        if entry as u32 == OVERFLOWED {
+            return Some(Slot::Overflowed);
+        }
+
+        Some(Slot::Held(Cell { g: C::from_u32(entry as u32), closed: entry & CLOSED != 0 }))
+    }
+
+    /// Stores `cell`, or marks it overflowed and returns false if its g is
+    /// too large for an entry, for the caller to keep elsewhere.
+    pub fn set<C: Cost>(&self, point: Point, cell: Cell<C>) -> bool {
+        let g = cell.g.to_u32().filter(|g| *g != OVERFLOWED);
+        let generation = (self.generation.load(Ordering::Relaxed) as u64) << GENERATION_SHIFT;
+        let closed = if cell.closed { CLOSED } else { 0 };
+
+        self.entries[self.index(point)].store(generation | closed | g.unwrap_or(OVERFLOWED) as u64, Ordering::Relaxed);
+        g.is_some()
+    }
+}
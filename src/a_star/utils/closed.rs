@@ -0,0 +1,72 @@
+use std::{
+    collections::{HashMap, hash_map::DefaultHasher},
+    hash::{Hash, Hasher},
+    sync::Mutex
+};
+use super::{structs::Point, cost::Cost, helpers};
+
+/// What a closed table knows about one cell.
+#[derive(Copy, Clone, Debug)]
+pub struct Seen<C: Cost> {
+    pub g: C,
+    pub parent: Point,
+    /// Whether the copy with this g has been expanded.
+    pub expanded: bool,
+}
+
+/// Best known g and parent of every generated cell, shared by all threads of
+/// a search for duplicate detection. Sharded by point hash, so threads only
+/// contend when they touch cells in the same shard at the same time.
+pub struct ClosedTable<C: Cost> {
+    shards: Vec<Mutex<HashMap<Point, Seen<C>>>>,
+}
+
+impl<C: Cost> ClosedTable<C> {
+    pub fn new(shards: usize) -> ClosedTable<C> {
+        ClosedTable { shards: (0..shards.max(1)).map(|_| Mutex::new(HashMap::new())).collect() }
+    }
+
+    fn shard(&self, point: &Point) -> &Mutex<HashMap<Point, Seen<C>>> {
+        let mut state = DefaultHasher::new();
+
+        point.hash(&mut state);
+        &self.shards[(state.finish() % self.shards.len() as u64) as usize]
+    }
+
+    /// Records `point` at `g` through `parent` unless it is already known at
+    /// no greater cost. Gives `None` for a duplicate, otherwise whether an
+    /// expanded copy has just been reopened.
+    pub fn improve(&self, point: Point, g: C, parent: Point) -> Option<bool> {
+        let mut shard = self.shard(&point).lock().unwrap();
+        let reopened = match shard.get(&point) {
+            Some(seen) if seen.g <= g => return None,
+            Some(seen) => seen.expanded,
+            None => false,
+        };
+
+        shard.insert(point, Seen { g, parent, expanded: false });
+        Some(reopened)
+    }
+
+    /// Marks the copy of `point` at `g` expanded. False if a cheaper copy
+    /// has been recorded since, so this one is stale.
+    pub fn expand(&self, point: Point, g: C) -> bool {
+        match self.shard(&point).lock().unwrap().get_mut(&point) {
+            Some(seen) if seen.g < g => false,
+            Some(seen) => {
+                seen.expanded = true;
+                true
+            },
+            None => true,
+        }
+    }
+
+    pub fn get(&self, point: Point) -> Option<Seen<C>> {
+        self.shard(&point).lock().unwrap().get(&point).copied()
+    }
+
+    /// Path from the start to `end`, filled in cell by cell.
+    pub fn path(&self, end: Point, limit: usize) -> Vec<Point> {
+        helpers::fill_path(helpers::trace_path(end, limit, |point| self.get(point).map(|seen| seen.parent)))
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    cmp::Ordering,
+    convert::TryFrom,
+    fmt,
+    ops::Add
+};
+
+/// Path cost. Grid moves cost whole numbers of steps, so the integer costs
+/// are exact there and `u32` keeps nodes small; `Real` keeps real-valued
+/// costs, such as any-angle lengths, exact.
+pub trait Cost: Copy + Ord + Add<Output = Self> + Default + Send + Sync + fmt::Debug + fmt::Display + 'static {
+    const ZERO: Self;
+    /// Above every path cost, standing for no path.
+    const INFINITY: Self;
+    /// Whether costs are whole numbers, which `GridTable` packs into 32 bits.
+    const INTEGER: bool;
+
+    fn from_u32(value: u32) -> Self;
+    /// Exact as a `u32`, if it is one.
+    fn to_u32(self) -> Option<u32>;
+    /// Nearest cost to a length.
+    fn from_f64(value: f64) -> Self;
+    /// Largest cost not above an estimate, so admissible estimates stay admissible.
+    fn floor(value: f64) -> Self;
+    fn to_f64(self) -> f64;
+}
+
+macro_rules! integer_cost {
+    ($($t:ty),*) => {$(
+        impl Cost for $t {
+            const ZERO: $t = 0;
+            const INFINITY: $t = <$t>::MAX;
+            const INTEGER: bool = true;
+
+            fn from_u32(value: u32) -> $t {
+                value as $t
+            }
+
+            fn to_u32(self) -> Option<u32> {
+                u32::try_from(self).ok()
+            }
+
+            fn from_f64(value: f64) -> $t {
+                value.round() as $t
+            }
+
+            fn floor(value: f64) -> $t {
+                value.floor() as $t
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+        }
+    )*};
+}
+
+integer_cost!(u32, u64, i128);
+
+/// Real-valued cost, ordered by `f64::total_cmp` so it can key open lists.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Real(pub f64);
+
+impl PartialEq for Real {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Real {}
+
+impl Ord for Real {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl PartialOrd for Real {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for Real {
+    type Output = Real;
+
+    fn add(self, other: Real) -> Real {
+        Real(self.0 + other.0)
+    }
+}
+
+impl fmt::Display for Real {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Cost for Real {
+    const ZERO: Real = Real(0.0);
+    const INFINITY: Real = Real(f64::INFINITY);
+    const INTEGER: bool = false;
+
+    fn from_u32(value: u32) -> Real {
+        Real(value as f64)
+    }
+
+    fn to_u32(self) -> Option<u32> {
+        if self.0.fract() == 0.0 && self.0 >= 0.0 && self.0 <= u32::MAX as f64 { Some(self.0 as u32) } else { None }
+    }
+
+    fn from_f64(value: f64) -> Real {
+        Real(value)
+    }
+
+    fn floor(value: f64) -> Real {
+        Real(value)
+    }
+
+    fn to_f64(self) -> f64 {
+        self.0
+    }
+}
@@ -0,0 +1,129 @@
+use ::atomic::Atomic;
+use std::sync::{Arc, Mutex, atomic::{AtomicBool, AtomicU64, Ordering}};
+use super::{
+    structs::{Incumbent, Node, Point, Flags, Anytime},
+    cost::Cost,
+    open_list::OpenList,
+    parents::ParentTable,
+    cell_table::GridTable,
+    limits::{Budget, Limits}
+};
+
+/// State shared by all threads of one hash-distributed search, "hda" or
+/// "dpa", where every node is sent to the thread its cell hashes to.
+pub struct Shared<C: Cost> {
+    pub incumbent: Atomic<Incumbent<C>>,
+    // Messages, which for HDA* are batches rather than nodes.
+    pub sent_messages: AtomicU64,
+    pub received_messages: AtomicU64,
+    // Whether each thread may still have a node that could improve the
+    // incumbent. Only cleared by the thread itself once it has none, and set
+    // again before it counts a message as received.
+    pub working: Vec<AtomicBool>,
+    // Set once termination has been detected.
+    pub done: AtomicBool,
+    // Set once a limit has been hit.
+    pub stop: AtomicBool,
+    pub budget: Budget,
+    pub parents: ParentTable,
+    // Cheapest path to the goal traced so far, with what it costs.
+    pub best: Mutex<Option<(C, Vec<Point>)>>,
+    // Best g of every cell on grids small enough to index, written by each
+    // cell's owner only. Threads keep their own hashed tables otherwise.
+    pub cells: Option<Arc<GridTable>>,
+    // Longest possible path, guards parent walks.
+    pub limit: usize,
+}
+
+impl<C: Cost> Shared<C> {
+    /// State for `threads` threads searching from `start` on a map `size`
+    /// cells across.
+    pub fn new(start: Node<C>, threads: usize, limits: Limits, cells: Option<Arc<GridTable>>, size: usize) -> Shared<C> {
+        let shared = Shared {
+            incumbent: Atomic::new(Incumbent::new(start, C::INFINITY)),
+            sent_messages: AtomicU64::new(0),
+            received_messages: AtomicU64::new(0),
+            working: (0..threads).map(|_| AtomicBool::new(true)).collect(),
+            done: AtomicBool::new(false),
+            stop: AtomicBool::new(false),
+            budget: Budget::new(limits),
+            parents: ParentTable::new(threads),
+            best: Mutex::new(None),
+            cells,
+            limit: size * size,
+        };
+
+        shared.parents.insert(start.position, Point::default());
+        shared
+    }
+
+    /// Traces the path to `goal` once a thread has made it the incumbent, and
+    /// keeps it, reporting it to `anytime`, if it is cheaper than the one kept
+    /// so far. Threads keep improving the parents it is traced from, so it is
+    /// costed by the trace itself rather than the incumbent's g.
+    pub fn record(&self, goal: Point, anytime: Option<&Anytime>) {
+        let (cost, path) = self.parents.path(goal, self.limit);
+        let mut best = self.best.lock().unwrap();
+
+        if best.as_ref().is_some_and(|(kept, _)| *kept <= cost) {
+            return;
+        }
+
+        if let Some(anytime) = anytime {
+            anytime.report(cost, path.clone());
+        }
+
+        *best = Some((cost, path));
+    }
+
+    /// The path kept by `record` and its cost, no path if there is none.
+    pub fn solution(&self) -> (C, Vec<Point>) {
+        self.best.lock().unwrap().take().unwrap_or((C::INFINITY, Vec::new()))
+    }
+
+    /// Counts a message taken off `thread`'s channel, marking the thread busy first.
+    pub fn receive<M>(&self, thread: usize, message: M) -> M {
+        self.working[thread].store(true, Ordering::SeqCst);
+        self.received_messages.fetch_add(1, Ordering::SeqCst);
+        message
+    }
+
+    /// Whether every thread is idle with no message in flight. Receipts are
+    /// read before sends, and sends are counted before the message goes out,
+    /// so equal totals mean nothing was sent or received while the flags were
+    /// read. An idle thread only gets work by receiving, so all of them were
+    /// idle at once and the search is over.
+    pub fn terminated(&self) -> bool {
+        let received = self.received_messages.load(Ordering::SeqCst);
+        let idle = self.working.iter().all(|w| !w.load(Ordering::SeqCst));
+
+        idle && self.sent_messages.load(Ordering::SeqCst) == received
+    }
+
+    /// Whether `open` still holds a node that could improve the incumbent.
+    /// Anytime runs order by weighted f but prune on g + h, and an
+    /// inadmissible h can overestimate, so the bound tells neither; any node
+    /// counts then, and `stale` drops the ones that cannot as they are popped.
+    pub fn has_work(&self, open: &OpenList<C>, flags: &Flags) -> bool {
+        if flags.anytime.is_some() || !flags.heur.admissible() {
+            return !open.is_empty();
+        }
+
+        match open.bound() {
+            Some(bound) => bound < self.incumbent.load(Ordering::SeqCst).cost,
+            None => false,
+        }
+    }
+
+    /// Whether a popped node can no longer improve the incumbent. Only g is
+    /// known not to overestimate when h is inadmissible.
+    pub fn stale(&self, node: &Node<C>, flags: &Flags) -> bool {
+        let cost = self.incumbent.load(Ordering::SeqCst).cost;
+
+        if !flags.heur.admissible() {
+            return node.g >= cost;
+        }
+
+        flags.anytime.is_some() && node.g + node.h >= cost
+    }
+}
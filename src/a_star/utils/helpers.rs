@@ -1,93 +1,160 @@
-use std::{collections::{HashSet, hash_map::DefaultHasher}, hash::{Hash, Hasher}, usize};
-use super::structs::{HeurType, Node, Point};
+use std::{cmp, collections::hash_map::DefaultHasher, hash::{Hash, Hasher}};
+use super::{structs::{Node, Point}, heuristics::Heuristic, cost::Cost};
 
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
-use std::{thread, time};
-use rand::Rng;
+use std::thread;
 
-fn euclidean(node: Node, end: Node) -> i128 {
-    (((end.position.x - node.position.x).pow(2) + (end.position.y - node.position.y).pow(2)) as f32)
-        .sqrt() as i128
+/// Estimate from `node` to `end`, rounded down to a cost.
+pub fn heuristic<C: Cost>(node: Node<C>, end: Node<C>, heur: &dyn Heuristic) -> C {
+    C::floor(heur.estimate(node.position, end.position))
 }
 
-fn manhattan(node: Node, end: Node) -> i128 {
-    ((node.position.x - end.position.x).abs() + (node.position.y - end.position.y).abs()) as i128
+/// Basic bounds checking
+pub fn is_valid_neighbor<C: Cost>(graph: &[Vec<char>], node: &Node<C>, x: i32, y: i32) -> bool {
+    let (x0, y0) = (node.position.x + x, node.position.y + y);
+
+    x0 >= 0 && y0 >= 0 && x0 < graph.len() as i32 && y0 < graph.len() as i32
+        && graph[x0 as usize][y0 as usize] != 'W'
 }
 
-fn random_wait() {
-    //need to import via cargo
-    //let mut rng = rand::thread_rng();
+/// Thread that owns `node`, by its hash. Every copy of a node goes to its
+/// owner, so duplicates always meet in the same closed list.
+pub fn compute_recipient<C: Cost>(node: &Node<C>, num_threads: u64) -> usize {
+    let mut state = DefaultHasher::new();
 
-    //let time = rng.gen_range(1..5);
-    let rand_millis = time::Duration::from_millis(1);
-    thread::sleep(rand_millis);
+    node.hash(&mut state);
+
+    (state.finish() % num_threads) as usize
 }
 
-fn expensive(node: Node, end: Node) -> i128 {
-    random_wait();
+/// Follows parent pointers back from `end` and returns the path from the root.
+/// Stops at the start's default parent, a missing entry, or after `limit` steps.
+pub fn trace_path<F>(end: Point, limit: usize, parent_of: F) -> Vec<Point>
+    where F: Fn(Point) -> Option<Point> {
+    let mut path = vec![end];
+    let mut current = end;
 
-    euclidean(node, end)
-}
+    while let Some(parent) = parent_of(current) {
+        if parent == Point::default() || path.len() > limit {
+            break;
+        }
 
-fn non_admissible(node: Node, end: Node, expensive: bool) -> i128 {
-    if expensive {
-        random_wait();
+        path.push(parent);
+        current = parent;
     }
 
-    let mut rng = rand::thread_rng();
+    path.reverse();
+    path
+}
 
-    let percent = rng.gen_range(1.0..100.0);
+/// Joins consecutive points more than a step apart in a straight line, as
+/// left by jump point search, with the cells between them.
+pub fn fill_path(points: Vec<Point>) -> Vec<Point> {
+    let mut path = Vec::with_capacity(points.len());
 
-    let dist = euclidean(node, end);
+    for (i, point) in points.iter().enumerate() {
+        if let Some(previous) = i.checked_sub(1).map(|j| points[j]) {
+            let (dx, dy) = ((point.x - previous.x).signum(), (point.y - previous.y).signum());
+            let mut step = previous;
 
-    let result = (dist as f64) + (percent / 100.0) * (dist as f64);
+            // Runs are straight, anything else is passed through as is.
+            while (dx == 0 || dy == 0) && step != *point {
+                step = Point { x: step.x + dx, y: step.y + dy };
 
-    result as i128
-}
+                if step != *point {
+                    path.push(step);
+                }
+            }
+        }
 
-pub fn heuristic(node: Node, end: Node, heur: &HeurType) -> i128 {
-    match heur {
-        HeurType::EuclideanDist => euclidean(node, end),
-        HeurType::ManhattanDist => manhattan(node, end),
-        HeurType::Expensive => expensive(node, end),
-        HeurType::NonAdmissible => non_admissible(node, end, false),
-        HeurType::ExpensiveNonAdmissible => non_admissible(node, end, true)
+        path.push(*point);
     }
+
+    path
 }
 
-/// Basic bounds checking
-pub fn is_valid_neighbor(graph: &Vec<Vec<char>>, node: &Node, x: i32, y: i32) -> bool {
-    let (x0, y0) = (node.position.x + x, node.position.y + y);
+/// Whether the straight segment between the centres of two cells stays clear
+/// of 'W' cells. Every cell the segment touches is checked, and where it
+/// passes exactly through a corner both cells beside the corner must be clear.
+pub fn line_of_sight(graph: &[Vec<char>], from: Point, to: Point) -> bool {
+    let clear = |x: i32, y: i32| x >= 0 && y >= 0 && x < graph.len() as i32 && y < graph.len() as i32
+        && graph[x as usize][y as usize] != 'W';
+    let (dx, dy) = ((to.x - from.x).abs(), (to.y - from.y).abs());
+    let (step_x, step_y) = ((to.x - from.x).signum(), (to.y - from.y).signum());
+    let (mut x, mut y) = (from.x, from.y);
+    let mut error = dx - dy;
+    // Cells left to visit after the current one.
+    let mut cells = dx + dy;
+
+    loop {
+        if !clear(x, y) {
+            return false;
+        }
 
-    x0 >= 0 && y0 >= 0 && x0 < graph.len() as i32 && y0 < graph.len() as i32
-        && graph[x0 as usize][y0 as usize] != 'W'
-}
+        if cells == 0 {
+            return true;
+        }
 
-/// Calculate index using hashed node for thread to send Buffer() to.
-/// Returns -1 if in last thread.
-pub fn compute_recipient(node: &Node, setty: &HashSet<i32>, num_threads: u64, thread_num: usize) -> i32 {
-    let mut index;
-    let hash = (|| {
-        let mut state = DefaultHasher::new();
-
-        (&node).hash(&mut state);
-        state.finish()
-    })();
-
-    for i in 0..num_threads {
-        index = (hash + i) % num_threads;
-        // Makes sure we don't index the same thread's channel or a dead channel
-        if setty.contains(&(index as i32)) {
-            continue;
+        if error > 0 {
+            x += step_x;
+            error -= 2 * dy;
+        }
+        else if error < 0 {
+            y += step_y;
+            error += 2 * dx;
         }
         else {
-            return index as i32;
+            if !clear(x + step_x, y) || !clear(x, y + step_y) {
+                return false;
+            }
+
+            x += step_x;
+            y += step_y;
+            error += 2 * (dx - dy);
+            cells -= 1;
+        }
+
+        cells -= 1;
+    }
+}
+
+/// Euclidean length of a path through cell centres.
+pub fn path_length(path: &[Point]) -> f64 {
+    path.windows(2)
+        .map(|pair| (((pair[1].x - pair[0].x).pow(2) + (pair[1].y - pair[0].y).pow(2)) as f64).sqrt())
+        .sum()
+}
+
+/// String-pulls a path: drops every waypoint the path can cut straight past
+/// with a clear line of sight, leaving only the corners it has to turn at.
+pub fn smooth_path(graph: &[Vec<char>], path: &[Point]) -> Vec<Point> {
+    let mut smoothed: Vec<Point> = path.iter().take(1).copied().collect();
+
+    for i in 1..path.len() {
+        let anchor = *smoothed.last().unwrap();
+
+        if i + 1 == path.len() || !line_of_sight(graph, anchor, path[i + 1]) {
+            smoothed.push(path[i]);
         }
     }
-    
-    return thread_num as i32;
+
+    smoothed
+}
+
+/// Maps `work` over `items`, each thread taking an even share.
+pub fn in_parallel<T: Sync, R: Send>(items: &[T], threads: usize, work: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let share = cmp::max(1, items.len().div_ceil(cmp::max(threads, 1)));
+    let work = &work;
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = items.chunks(share)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(work).collect::<Vec<R>>()))
+            .collect();
+
+        handles.into_iter().flat_map(|handle| handle.join().expect("Panic")).collect()
+    })
 }
 
 pub fn parse_graph(graph_file: Option<&str>) -> (Vec<Vec<char>>, Point, Point) {
@@ -104,13 +171,13 @@ pub fn parse_graph(graph_file: Option<&str>) -> (Vec<Vec<char>>, Point, Point) {
     let mut start_point = Point::default();
     let mut end_point = Point::default();
 
-    for i in 0..graph.len() {
-        for j in 0..graph.len() {
-            if graph[i][j] == 'S' {
+    for (i, row) in graph.iter().enumerate() {
+        for (j, cell) in row.iter().enumerate() {
+            if *cell == 'S' {
                 start_point.x = i as i32;
                 start_point.y = j as i32;
             }
-            if graph[i][j] == 'E' {
+            if *cell == 'E' {
                 end_point.x = i as i32;
                 end_point.y = j as i32;
             }
@@ -0,0 +1,233 @@
+use std::{collections::BTreeMap, hint, sync::Arc, thread, time::{Duration, Instant}};
+use super::{structs::Point, landmarks::Landmarks};
+
+/// Estimate of the cost between two cells. Implementations may carry state
+/// such as precomputed tables, and are shared by every thread of a search.
+/// Estimates are exact reals; searches with integer costs round them down.
+pub trait Heuristic: Send + Sync {
+    fn estimate(&self, from: Point, to: Point) -> f64;
+
+    /// Whether estimates never exceed the true cost. Searches that stop once
+    /// no node's f can beat the best path found need this to stay optimal.
+    fn admissible(&self) -> bool {
+        true
+    }
+}
+
+/// Straight-line distance.
+pub struct Euclidean;
+
+/// Grid distance ignoring walls, exact on open ground.
+pub struct Manhattan;
+
+/// Euclidean distance after a synthetic delay, standing in for a costly heuristic.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Expensive {
+    pub cost: CostModel,
+}
+
+/// Euclidean distance inflated by 1 to 100 percent. The amount comes from
+/// hashing the seed with both cells, so a pair always gets the same estimate
+/// on every thread and every run.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct NonAdmissible {
+    pub seed: u64,
+}
+
+/// `NonAdmissible` after the same delay as `Expensive`.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ExpensiveNonAdmissible {
+    pub cost: CostModel,
+    pub noise: NonAdmissible,
+}
+
+/// How a synthetic heuristic spends its time.
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub enum Wait {
+    /// Gives the core up, so other threads can run meanwhile.
+    #[default]
+    Sleep,
+    /// Keeps the core busy, like a heuristic that actually computes something.
+    Spin,
+}
+
+/// How long each evaluation takes.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Delay {
+    Fixed(Duration),
+    /// Anywhere from the first to the second, evenly.
+    Uniform(Duration, Duration),
+    /// Exponentially distributed with this mean: mostly quick, now and then slow.
+    Exponential(Duration),
+}
+
+/// Synthetic cost of one heuristic evaluation. Delays are drawn by hashing
+/// the seed with the cells, so a run takes the same time for the same node
+/// wherever it is evaluated.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct CostModel {
+    pub wait: Wait,
+    pub delay: Delay,
+    pub seed: u64,
+}
+
+impl Default for CostModel {
+    /// Sleeps 1ms.
+    fn default() -> CostModel {
+        CostModel { wait: Wait::Sleep, delay: Delay::Fixed(Duration::from_millis(1)), seed: 0 }
+    }
+}
+
+impl CostModel {
+    fn duration(&self, from: Point, to: Point) -> Duration {
+        match self.delay {
+            Delay::Fixed(duration) => duration,
+            Delay::Uniform(low, high) => low + (high.saturating_sub(low)).mul_f64(unit(self.seed, from, to)),
+            // Inverse transform, with 1 - u kept away from zero.
+            Delay::Exponential(mean) => mean.mul_f64(-(1.0 - unit(self.seed, from, to)).ln()),
+        }
+    }
+
+    fn pay(&self, from: Point, to: Point) {
+        let duration = self.duration(from, to);
+
+        match self.wait {
+            Wait::Sleep => thread::sleep(duration),
+            Wait::Spin => {
+                let start = Instant::now();
+
+                while start.elapsed() < duration {
+                    hint::spin_loop();
+                }
+            },
+        }
+    }
+}
+
+/// SplitMix64 finaliser. Unlike `DefaultHasher` it is fixed, so seeds give
+/// the same numbers across builds.
+pub fn mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Uniform in [0, 1), fixed by the seed and the pair of cells.
+fn unit(seed: u64, from: Point, to: Point) -> f64 {
+    let cells = [from.x, from.y, to.x, to.y].iter().fold(mix(seed), |hash, value| mix(hash ^ *value as u32 as u64));
+
+    (cells >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn euclidean(from: Point, to: Point) -> f64 {
+    (((to.x - from.x).pow(2) + (to.y - from.y).pow(2)) as f64).sqrt()
+}
+
+fn manhattan(from: Point, to: Point) -> f64 {
+    ((from.x - to.x).abs() + (from.y - to.y).abs()) as f64
+}
+
+impl Heuristic for Euclidean {
+    fn estimate(&self, from: Point, to: Point) -> f64 {
+        euclidean(from, to)
+    }
+}
+
+impl Heuristic for Manhattan {
+    fn estimate(&self, from: Point, to: Point) -> f64 {
+        manhattan(from, to)
+    }
+}
+
+impl Heuristic for Expensive {
+    fn estimate(&self, from: Point, to: Point) -> f64 {
+        self.cost.pay(from, to);
+
+        euclidean(from, to)
+    }
+}
+
+impl Heuristic for NonAdmissible {
+    fn estimate(&self, from: Point, to: Point) -> f64 {
+        let percent = 1.0 + 99.0 * unit(self.seed, from, to);
+        let dist = euclidean(from, to);
+
+        dist + (percent / 100.0) * dist
+    }
+
+    fn admissible(&self) -> bool {
+        false
+    }
+}
+
+impl Heuristic for ExpensiveNonAdmissible {
+    fn estimate(&self, from: Point, to: Point) -> f64 {
+        self.cost.pay(from, to);
+
+        self.noise.estimate(from, to)
+    }
+
+    fn admissible(&self) -> bool {
+        false
+    }
+}
+
+impl Heuristic for Landmarks {
+    // Manhattan distance is exact on open ground, where landmark bounds are weakest.
+    fn estimate(&self, from: Point, to: Point) -> f64 {
+        (self.lower_bound(from, to) as f64).max(manhattan(from, to))
+    }
+}
+
+type Factory = Box<dyn Fn(&[Vec<char>]) -> Arc<dyn Heuristic> + Send + Sync>;
+
+/// Heuristics by name, for choosing one from the command line or a config.
+/// Each entry builds its heuristic for a given graph, so ones that need
+/// preprocessing can do it there.
+pub struct Registry {
+    factories: BTreeMap<String, Factory>,
+}
+
+impl Registry {
+    /// A registry with nothing in it.
+    pub fn new() -> Registry {
+        Registry { factories: BTreeMap::new() }
+    }
+
+    /// Adds `name`, replacing any heuristic already registered under it.
+    pub fn register<F>(&mut self, name: &str, factory: F)
+        where F: Fn(&[Vec<char>]) -> Arc<dyn Heuristic> + Send + Sync + 'static {
+        self.factories.insert(name.to_string(), Box::new(factory));
+    }
+
+    pub fn build(&self, name: &str, graph: &[Vec<char>]) -> Option<Arc<dyn Heuristic>> {
+        self.factories.get(name).map(|factory| factory(graph))
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.factories.keys().map(String::as_str).collect()
+    }
+
+    /// The built-in heuristics under their command line names, with `seed`
+    /// for the non-admissible noise and `cost` for the expensive ones.
+    pub fn built_in(seed: u64, cost: CostModel) -> Registry {
+        let mut registry = Registry::new();
+        let noise = NonAdmissible { seed };
+
+        registry.register("euclidean", |_| Arc::new(Euclidean));
+        registry.register("manhattan", |_| Arc::new(Manhattan));
+        registry.register("expensive", move |_| Arc::new(Expensive { cost }));
+        registry.register("nonadmissible", move |_| Arc::new(noise));
+        registry.register("expnon", move |_| Arc::new(ExpensiveNonAdmissible { cost, noise }));
+
+        registry
+    }
+}
+
+impl Default for Registry {
+    /// The built-ins with seed 0 and the default cost model.
+    fn default() -> Registry {
+        Registry::built_in(0, CostModel::default())
+    }
+}
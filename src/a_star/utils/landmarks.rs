@@ -0,0 +1,457 @@
+use std::{
+    cmp,
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path
+};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+use super::{structs::Point, helpers};
+
+/// Distance of a cell a landmark cannot reach.
+pub const UNREACHED: u32 = u32::MAX;
+const NO_PARENT: usize = usize::MAX;
+// Random roots tried per avoid landmark before giving up on placing more.
+const AVOID_ATTEMPTS: usize = 8;
+// Start of every saved landmark file, followed by the format version.
+const MAGIC: &[u8; 4] = b"PADH";
+const VERSION: u8 = 1;
+
+/// How landmarks are placed.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Selection {
+    /// Each landmark is the cell farthest from all the ones before it.
+    Farthest,
+    /// Each landmark is the leaf of a random shortest-path tree whose subtree
+    /// the current landmarks bound worst (Goldberg and Werneck).
+    Avoid,
+    /// Uniformly random open cells.
+    Random,
+}
+
+impl Selection {
+    fn code(&self) -> u8 {
+        match self {
+            Selection::Farthest => 0,
+            Selection::Avoid => 1,
+            Selection::Random => 2,
+        }
+    }
+}
+
+/// One landmark's distances, stored in the narrowest width that holds them.
+/// Each width's largest value stands for `UNREACHED`.
+enum Table {
+    Byte(Vec<u8>),
+    Short(Vec<u16>),
+    Word(Vec<u32>),
+}
+
+impl Table {
+    fn new(distances: Vec<u32>) -> Table {
+        let longest = distances.iter().copied().filter(|d| *d != UNREACHED).max().unwrap_or(0);
+
+        if longest < u8::MAX as u32 {
+            Table::Byte(distances.iter().map(|d| cmp::min(*d, u8::MAX as u32) as u8).collect())
+        }
+        else if longest < u16::MAX as u32 {
+            Table::Short(distances.iter().map(|d| cmp::min(*d, u16::MAX as u32) as u16).collect())
+        }
+        else {
+            Table::Word(distances)
+        }
+    }
+
+    fn get(&self, cell: usize) -> u32 {
+        match self {
+            Table::Byte(table) if table[cell] == u8::MAX => UNREACHED,
+            Table::Byte(table) => table[cell] as u32,
+            Table::Short(table) if table[cell] == u16::MAX => UNREACHED,
+            Table::Short(table) => table[cell] as u32,
+            Table::Word(table) => table[cell],
+        }
+    }
+
+    fn width(&self) -> u8 {
+        match self {
+            Table::Byte(_) => 1,
+            Table::Short(_) => 2,
+            Table::Word(_) => 4,
+        }
+    }
+
+    fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(&[self.width()])?;
+
+        match self {
+            Table::Byte(table) => out.write_all(table),
+            Table::Short(table) => table.iter().try_for_each(|d| out.write_all(&d.to_le_bytes())),
+            Table::Word(table) => table.iter().try_for_each(|d| out.write_all(&d.to_le_bytes())),
+        }
+    }
+
+    fn read(input: &mut impl Read, cells: usize) -> io::Result<Table> {
+        let width = read_bytes::<1>(input)?[0];
+        let mut bytes = vec![0; cells * width as usize];
+        input.read_exact(&mut bytes)?;
+
+        match width {
+            1 => Ok(Table::Byte(bytes)),
+            2 => Ok(Table::Short(bytes.chunks_exact(2).map(|d| u16::from_le_bytes([d[0], d[1]])).collect())),
+            4 => Ok(Table::Word(bytes.chunks_exact(4).map(|d| u32::from_le_bytes([d[0], d[1], d[2], d[3]])).collect())),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "unknown table width")),
+        }
+    }
+}
+
+fn read_bytes<const N: usize>(input: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    input.read_exact(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+    read_bytes(input).map(u64::from_le_bytes)
+}
+
+/// FNV-1a over the graph's cells, tying saved tables to one map's contents.
+fn fingerprint(graph: &[Vec<char>]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+
+    for value in std::iter::once(graph.len() as u32).chain(graph.iter().flatten().map(|cell| *cell as u32)) {
+        for byte in value.to_le_bytes() {
+            hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+
+    hash
+}
+
+/// Breadth-first shortest-path tree, which is Dijkstra with unit edge costs.
+struct Tree {
+    distances: Vec<u32>,
+    parents: Vec<usize>,
+    // Reached cells, nearest first.
+    order: Vec<usize>,
+}
+
+fn shortest_paths(graph: &[Vec<char>], source: Point) -> Tree {
+    let size = graph.len();
+    let mut tree = Tree { distances: vec![UNREACHED; size * size], parents: vec![NO_PARENT; size * size], order: Vec::new() };
+    let mut queue = VecDeque::from([source.x as usize * size + source.y as usize]);
+    tree.distances[queue[0]] = 0;
+
+    while let Some(cell) = queue.pop_front() {
+        let (x, y) = ((cell / size) as i32, (cell % size) as i32);
+        tree.order.push(cell);
+
+        for (dx, dy) in [(0, 1), (-1, 0), (1, 0), (0, -1)] {
+            let (nx, ny) = (x + dx, y + dy);
+
+            if nx < 0 || ny < 0 || nx >= size as i32 || ny >= size as i32 || graph[nx as usize][ny as usize] == 'W' {
+                continue;
+            }
+
+            let next = nx as usize * size + ny as usize;
+
+            if tree.distances[next] == UNREACHED {
+                tree.distances[next] = tree.distances[cell] + 1;
+                tree.parents[next] = cell;
+                queue.push_back(next);
+            }
+        }
+    }
+
+    tree
+}
+
+/// Breadth-first distances from `source`, with each level's frontier split
+/// over `threads` threads. Only the distances are kept, so unlike
+/// `shortest_paths` there is no tree.
+fn distances(graph: &[Vec<char>], source: Point, threads: usize) -> Vec<u32> {
+    if threads <= 1 {
+        return shortest_paths(graph, source).distances;
+    }
+
+    let size = graph.len();
+    let mut distances = vec![UNREACHED; size * size];
+    let mut frontier = vec![source.x as usize * size + source.y as usize];
+    let mut level = 0;
+    distances[frontier[0]] = 0;
+
+    while !frontier.is_empty() {
+        let chunks: Vec<&[usize]> = frontier.chunks(frontier.len().div_ceil(threads)).collect();
+        let known = &distances;
+        // Neighbours each thread found unreached, which may repeat across threads.
+        let reached = helpers::in_parallel(&chunks, threads, |chunk| {
+            let mut reached = Vec::new();
+
+            for cell in chunk.iter() {
+                let (x, y) = ((cell / size) as i32, (cell % size) as i32);
+
+                for (dx, dy) in [(0, 1), (-1, 0), (1, 0), (0, -1)] {
+                    let (nx, ny) = (x + dx, y + dy);
+
+                    if nx < 0 || ny < 0 || nx >= size as i32 || ny >= size as i32 || graph[nx as usize][ny as usize] == 'W' {
+                        continue;
+                    }
+
+                    let next = nx as usize * size + ny as usize;
+
+                    if known[next] == UNREACHED {
+                        reached.push(next);
+                    }
+                }
+            }
+
+            reached
+        });
+
+        level += 1;
+        frontier.clear();
+
+        for next in reached.into_iter().flatten() {
+            if distances[next] == UNREACHED {
+                distances[next] = level;
+                frontier.push(next);
+            }
+        }
+    }
+
+    distances
+}
+
+/// Exact distances from a handful of landmark cells to every cell, giving the
+/// ALT lower bound |d(L, goal) - d(L, n)| through the triangle inequality.
+/// On undirected grids these are the same as differential heuristic tables.
+pub struct Landmarks {
+    size: usize,
+    // Settings the landmarks were placed with, checked when loading.
+    count: usize,
+    selection: Selection,
+    seed: u64,
+    points: Vec<Point>,
+    // One table per landmark, indexed by x * size + y.
+    distances: Vec<Table>,
+}
+
+impl Landmarks {
+    /// Places up to `count` landmarks and computes their distance tables on
+    /// `threads` threads. Random landmarks are known up front, so their
+    /// tables are computed side by side. Farthest and avoid place each
+    /// landmark from the tables of those before it, so theirs are computed in
+    /// turn, each one's search split level by level; avoid grows the trees
+    /// of its candidate roots side by side, `threads` at a time.
+    pub fn new(graph: &[Vec<char>], count: usize, selection: Selection, seed: u64, threads: usize) -> Landmarks {
+        let size = graph.len();
+        let open: Vec<Point> = (0..size as i32)
+            .flat_map(|x| (0..size as i32).map(move |y| Point { x, y }))
+            .filter(|point| graph[point.x as usize][point.y as usize] != 'W')
+            .collect();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut landmarks = Landmarks { size, count, selection, seed, points: Vec::new(), distances: Vec::new() };
+
+        if open.is_empty() {
+            return landmarks;
+        }
+
+        match selection {
+            Selection::Random => {
+                landmarks.points = open.choose_multiple(&mut rng, count).copied().collect();
+                landmarks.distances = helpers::in_parallel(&landmarks.points, threads, |point| Table::new(shortest_paths(graph, *point).distances));
+            },
+            Selection::Farthest => {
+                let root = open[rng.gen_range(0..open.len())];
+                // Distance from each cell to the nearest landmark, or to the root before there are any.
+                let mut nearest = distances(graph, root, threads);
+
+                while landmarks.points.len() < count {
+                    let (cell, distance) = match nearest.iter().enumerate().filter(|(_, d)| **d != UNREACHED).max_by_key(|(_, d)| **d) {
+                        Some((cell, distance)) => (cell, *distance),
+                        None => break,
+                    };
+
+                    if distance == 0 && !landmarks.points.is_empty() {
+                        break;
+                    }
+
+                    // The first landmark replaces the distances to the root.
+                    let first = landmarks.points.is_empty();
+                    let table = landmarks.add(graph, cell, threads);
+
+                    for (cell, known) in nearest.iter_mut().enumerate() {
+                        *known = if first { table.get(cell) } else { cmp::min(*known, table.get(cell)) };
+                    }
+                }
+            },
+            Selection::Avoid => {
+                let mut attempts = count * AVOID_ATTEMPTS;
+
+                // Roots come from the generator in the same order as one at a
+                // time, so the landmarks placed do not depend on `threads`.
+                while attempts > 0 && landmarks.points.len() < count {
+                    let roots: Vec<Point> = (0..cmp::min(cmp::max(threads, 1), attempts)).map(|_| open[rng.gen_range(0..open.len())]).collect();
+                    let trees = helpers::in_parallel(&roots, threads, |root| shortest_paths(graph, *root));
+                    attempts -= roots.len();
+
+                    for (root, tree) in roots.into_iter().zip(trees) {
+                        if landmarks.points.len() == count {
+                            break;
+                        }
+
+                        let root_cell = root.x as usize * size + root.y as usize;
+
+                        // Weight cells by how far the current bound falls short of their
+                        // true distance from the root, and total them up each subtree.
+                        let mut bounds: Vec<u32> = vec![0; size * size];
+                        let mut covered = vec![false; size * size];
+
+                        for table in landmarks.distances.iter().filter(|table| table.get(root_cell) != UNREACHED) {
+                            for (cell, bound) in bounds.iter_mut().enumerate() {
+                                let distance = table.get(cell);
+
+                                if distance != UNREACHED {
+                                    *bound = cmp::max(*bound, distance.abs_diff(table.get(root_cell)));
+                                }
+                            }
+                        }
+
+                        let mut weights: Vec<u64> = tree.distances.iter().zip(bounds.iter())
+                            .map(|(distance, bound)| if *distance == UNREACHED { 0 } else { (distance - bound) as u64 })
+                            .collect();
+
+                        for cell in landmarks.points.iter().map(|point| point.x as usize * size + point.y as usize) {
+                            covered[cell] = true;
+                        }
+
+                        for cell in tree.order.iter().rev().copied() {
+                            let parent = tree.parents[cell];
+
+                            if covered[cell] {
+                                weights[cell] = 0;
+                            }
+
+                            if parent != NO_PARENT {
+                                weights[parent] += weights[cell];
+                                covered[parent] |= covered[cell];
+                            }
+                        }
+
+                        // Walk down into the heaviest subtree until reaching a leaf.
+                        let mut cell = root_cell;
+
+                        while let Some(child) = [(0, 1), (-1, 0), (1, 0), (0, -1)].iter()
+                            .map(|(dx, dy)| ((cell / size) as i32 + dx, (cell % size) as i32 + dy))
+                            .filter(|(x, y)| *x >= 0 && *y >= 0 && *x < size as i32 && *y < size as i32)
+                            .map(|(x, y)| x as usize * size + y as usize)
+                            .filter(|child| tree.parents[*child] == cell && weights[*child] > 0)
+                            .max_by_key(|child| weights[*child]) {
+                            cell = child;
+                        }
+
+                        // Every subtree below the root is already bounded well.
+                        if cell != root_cell {
+                            landmarks.add(graph, cell, threads);
+                        }
+                    }
+                }
+            },
+        }
+
+        landmarks
+    }
+
+    /// Adds the landmark at `cell` and returns its distance table.
+    fn add(&mut self, graph: &[Vec<char>], cell: usize, threads: usize) -> &Table {
+        let point = Point { x: (cell / self.size) as i32, y: (cell % self.size) as i32 };
+
+        self.points.push(point);
+        self.distances.push(Table::new(distances(graph, point, threads)));
+        self.distances.last().unwrap()
+    }
+
+    fn bound(&self, from: usize, to: usize) -> i128 {
+        self.distances.iter()
+            .map(|table| (table.get(from), table.get(to)))
+            .filter(|(from, to)| *from != UNREACHED && *to != UNREACHED)
+            .map(|(from, to)| from.abs_diff(to) as i128)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Writes the landmarks and their tables to `path`, along with a
+    /// checksum of `graph` and the settings they were placed with.
+    pub fn save(&self, path: impl AsRef<Path>, graph: &[Vec<char>]) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION, self.selection.code()])?;
+
+        for value in [fingerprint(graph), self.size as u64, self.count as u64, self.seed, self.points.len() as u64] {
+            out.write_all(&value.to_le_bytes())?;
+        }
+
+        for (point, table) in self.points.iter().zip(self.distances.iter()) {
+            out.write_all(&point.x.to_le_bytes())?;
+            out.write_all(&point.y.to_le_bytes())?;
+            table.write(&mut out)?;
+        }
+
+        out.flush()
+    }
+
+    /// Reads landmarks saved by `save`. Fails with `InvalidData` unless they
+    /// were saved for this exact graph and the same placement settings.
+    pub fn load(path: impl AsRef<Path>, graph: &[Vec<char>], count: usize, selection: Selection, seed: u64) -> io::Result<Landmarks> {
+        let mut input = BufReader::new(File::open(path)?);
+        let stale = |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("saved landmarks have a different {}", what));
+
+        if &read_bytes::<4>(&mut input)? != MAGIC || read_bytes::<1>(&mut input)?[0] != VERSION {
+            return Err(stale("format"));
+        }
+
+        if read_bytes::<1>(&mut input)?[0] != selection.code() {
+            return Err(stale("selection"));
+        }
+
+        if read_u64(&mut input)? != fingerprint(graph) || read_u64(&mut input)? != graph.len() as u64 {
+            return Err(stale("map"));
+        }
+
+        if read_u64(&mut input)? != count as u64 || read_u64(&mut input)? != seed {
+            return Err(stale("landmark count or seed"));
+        }
+
+        let size = graph.len();
+        let placed = read_u64(&mut input)? as usize;
+
+        // Placement can fall short of `count` but never beat it.
+        if placed > count {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "saved landmarks hold more than their count"));
+        }
+
+        let mut landmarks = Landmarks { size, count, selection, seed, points: Vec::with_capacity(placed),
+                                        distances: Vec::with_capacity(placed) };
+
+        for _ in 0..placed {
+            let x = i32::from_le_bytes(read_bytes(&mut input)?);
+            let y = i32::from_le_bytes(read_bytes(&mut input)?);
+
+            landmarks.points.push(Point { x, y });
+            landmarks.distances.push(Table::read(&mut input, size * size)?);
+        }
+
+        Ok(landmarks)
+    }
+
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    /// Lower bound on the distance between two cells. Landmarks that cannot
+    /// reach both are skipped.
+    pub fn lower_bound(&self, from: Point, to: Point) -> i128 {
+        self.bound(from.x as usize * self.size + from.y as usize, to.x as usize * self.size + to.y as usize)
+    }
+}
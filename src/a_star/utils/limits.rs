@@ -0,0 +1,129 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Instant
+};
+
+/// Why a search returned.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum StopReason {
+    /// Ran to the end: the path is within the mode's bound, or there is none.
+    Completed,
+    Cancelled,
+    Deadline,
+    ExpansionLimit,
+    MemoryLimit,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self {
+            StopReason::Completed => "completed",
+            StopReason::Cancelled => "cancelled",
+            StopReason::Deadline => "deadline reached",
+            StopReason::ExpansionLimit => "expansion limit reached",
+            StopReason::MemoryLimit => "memory limit reached",
+        };
+
+        write!(f, "{}", reason)
+    }
+}
+
+/// Cooperative cancellation flag. Clones share the flag, so a caller keeps
+/// one and hands another to the search through `Limits`.
+#[derive(Clone, Default, Debug)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Hard caps on a single search. Unset limits are not checked.
+#[derive(Clone, Default, Debug)]
+pub struct Limits {
+    pub deadline: Option<Instant>,
+    pub max_expansions: Option<u64>,
+    /// Cap on nodes stored in the open lists, or in IDA*'s transposition
+    /// tables. Counted as insertions across all threads, so it bounds what
+    /// can be held at any one time.
+    pub max_entries: Option<u64>,
+    pub cancel: CancelToken,
+}
+
+/// Running totals of one search checked against its limits, shared by all
+/// of its threads.
+pub struct Budget {
+    limits: Limits,
+    expansions: AtomicU64,
+    entries: AtomicU64,
+    tripped: AtomicBool,
+    reason: Mutex<Option<StopReason>>,
+}
+
+impl Budget {
+    pub fn new(limits: Limits) -> Budget {
+        Budget {
+            limits,
+            expansions: AtomicU64::new(0),
+            entries: AtomicU64::new(0),
+            tripped: AtomicBool::new(false),
+            reason: Mutex::new(None),
+        }
+    }
+
+    pub fn expanded(&self) {
+        self.expansions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stored(&self) {
+        self.entries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Whether any limit has been hit. The first one seen is kept as the
+    /// reason the search stopped.
+    pub fn exceeded(&self) -> bool {
+        if self.tripped.load(Ordering::SeqCst) {
+            return true;
+        }
+
+        let hit = if self.limits.cancel.is_cancelled() {
+            Some(StopReason::Cancelled)
+        }
+        else if self.limits.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            Some(StopReason::Deadline)
+        }
+        else if self.limits.max_expansions.is_some_and(|max| self.expansions.load(Ordering::Relaxed) >= max) {
+            Some(StopReason::ExpansionLimit)
+        }
+        else if self.limits.max_entries.is_some_and(|max| self.entries.load(Ordering::Relaxed) >= max) {
+            Some(StopReason::MemoryLimit)
+        }
+        else {
+            None
+        };
+
+        match hit {
+            Some(reason) => {
+                self.reason.lock().unwrap().get_or_insert(reason);
+                self.tripped.store(true, Ordering::SeqCst);
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn reason(&self) -> StopReason {
+        self.reason.lock().unwrap().unwrap_or(StopReason::Completed)
+    }
+}
@@ -1,3 +1,15 @@
-pub mod dynamic_barrier;
+pub mod affinity;
+pub mod cell_table;
+pub mod closed;
+pub mod cost;
+pub mod distributed;
 pub mod helpers;
-pub mod structs;
\ No newline at end of file
+pub mod heuristics;
+pub mod landmarks;
+pub mod limits;
+pub mod open_list;
+pub mod parents;
+pub mod pool;
+pub mod stats;
+pub mod structs;
+pub mod successors;
\ No newline at end of file
@@ -0,0 +1,303 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    hash::{BuildHasherDefault, Hasher}
+};
+use std::ops::Bound::{Excluded, Included, Unbounded};
+use super::{structs::{Node, Point, SearchMode, TieBreak, Tie}, cost::Cost};
+
+/// Open list ordered for the configured search mode. Holds at most one node
+/// per cell: pushing a cell that is already in the list keeps the cheaper of
+/// the two, so no stale copies are left behind to skip or to take up memory.
+pub enum OpenList<C: Cost> {
+    Ordered(IndexedHeap<C>),
+    Focal(FocalList<C>),
+}
+
+impl<C: Cost> OpenList<C> {
+    pub fn new(mode: SearchMode, tie_break: TieBreak) -> OpenList<C> {
+        match mode {
+            SearchMode::Weighted(_) => OpenList::Ordered(IndexedHeap::new(tie_break)),
+            SearchMode::Focal(w) => OpenList::Focal(FocalList::new(w, tie_break)),
+        }
+    }
+
+    pub fn push(&mut self, node: Node<C>) {
+        match self {
+            OpenList::Ordered(heap) => heap.push(node),
+            OpenList::Focal(focal) => focal.push(node),
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<Node<C>> {
+        match self {
+            OpenList::Ordered(heap) => heap.pop(),
+            OpenList::Focal(focal) => focal.pop(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            OpenList::Ordered(heap) => heap.nodes.len(),
+            OpenList::Focal(focal) => focal.nodes.len(),
+        }
+    }
+
+    /// Lower bound on w·C* over the nodes left in the list. Once the incumbent
+    /// cost is no larger than this, nothing in the list can bring it further
+    /// inside the bound and the list may be treated as exhausted.
+    pub fn bound(&self) -> Option<C> {
+        match self {
+            OpenList::Ordered(heap) => heap.nodes.first().map(|(node, _)| node.f),
+            OpenList::Focal(focal) => focal.f_min().map(|f| focal.threshold_for(f)),
+        }
+    }
+}
+
+/// Multiply-rotate hash for cell coordinates. The heap index is updated at
+/// every level a node moves, where SipHash costs more than the heap itself.
+#[derive(Default)]
+struct CellHasher(u64);
+
+impl Hasher for CellHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_u64(*byte as u64);
+        }
+    }
+
+    fn write_i32(&mut self, value: i32) {
+        self.write_u64(value as u32 as u64);
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.0 = (self.0.rotate_left(5) ^ value).wrapping_mul(0x517c_c1b7_2722_0a95);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Binary heap of nodes by f, then by the tie-break policy, with an index
+/// from each cell to its place in the heap, so a cheaper path to a queued
+/// cell moves its node up in place.
+pub struct IndexedHeap<C: Cost> {
+    nodes: Vec<(Node<C>, Tie<C>)>,
+    index: HashMap<Point, usize, BuildHasherDefault<CellHasher>>,
+    tie_break: TieBreak,
+    seq: u64,
+}
+
+impl<C: Cost> IndexedHeap<C> {
+    pub fn new(tie_break: TieBreak) -> IndexedHeap<C> {
+        IndexedHeap { nodes: Vec::new(), index: HashMap::default(), tie_break, seq: 0 }
+    }
+
+    /// Adds `node`, or lowers the g of the node already queued for its cell.
+    /// A node no cheaper than the queued one is dropped.
+    pub fn push(&mut self, node: Node<C>) {
+        let tie = self.tie_break.key(&node, self.seq);
+        self.seq += 1;
+
+        match self.index.get(&node.position) {
+            Some(&i) if self.nodes[i].0.g <= node.g => (),
+            Some(&i) => {
+                self.nodes[i] = (node, tie);
+                // Usually decrease-key, but with an inconsistent or weighted
+                // f the cheaper node can rank lower.
+                self.sift_up(i);
+                self.sift_down(i);
+            },
+            None => {
+                self.nodes.push((node, tie));
+                self.index.insert(node.position, self.nodes.len() - 1);
+                self.sift_up(self.nodes.len() - 1);
+            },
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<Node<C>> {
+        let last = self.nodes.pop()?;
+        self.index.remove(&last.0.position);
+
+        if self.nodes.is_empty() {
+            return Some(last.0);
+        }
+
+        let (first, _) = self.nodes[0];
+        self.index.remove(&first.position);
+        self.nodes[0] = last;
+        self.sift_down(0);
+
+        Some(first)
+    }
+
+    /// Moves `entry` into slot `i`, keeping the index in step.
+    fn place(&mut self, i: usize, entry: (Node<C>, Tie<C>)) {
+        self.nodes[i] = entry;
+        self.index.insert(entry.0.position, i);
+    }
+
+    // Both sifts carry the entry along and only write it once they find its slot.
+    fn sift_up(&mut self, mut i: usize) {
+        let entry = self.nodes[i];
+
+        while i > 0 {
+            let parent = (i - 1) / 2;
+
+            if rank(&entry) >= rank(&self.nodes[parent]) {
+                break;
+            }
+
+            self.place(i, self.nodes[parent]);
+            i = parent;
+        }
+
+        self.place(i, entry);
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        let entry = self.nodes[i];
+
+        loop {
+            let mut child = 2 * i + 1;
+
+            if child >= self.nodes.len() {
+                break;
+            }
+
+            if child + 1 < self.nodes.len() && rank(&self.nodes[child + 1]) < rank(&self.nodes[child]) {
+                child += 1;
+            }
+
+            if rank(&self.nodes[child]) >= rank(&entry) {
+                break;
+            }
+
+            self.place(i, self.nodes[child]);
+            i = child;
+        }
+
+        self.place(i, entry);
+    }
+}
+
+/// Where a heap entry goes, lowest first: by f, then by its tie-break key.
+fn rank<C: Cost>(entry: &(Node<C>, Tie<C>)) -> (C, Tie<C>) {
+    (entry.0.f, entry.1)
+}
+
+impl<C: Cost> Default for IndexedHeap<C> {
+    fn default() -> IndexedHeap<C> {
+        IndexedHeap::new(TieBreak::default())
+    }
+}
+
+/// Focal search list: every node is kept ordered by f = g + h, and those
+/// within w·f_min are also kept ordered by h, then by the tie-break policy,
+/// which is the order they are expanded in.
+pub struct FocalList<C: Cost> {
+    weight: f64,
+    tie_break: TieBreak,
+    seq: u64,
+    // None until the first node arrives.
+    threshold: Option<C>,
+    nodes: HashMap<u64, (Node<C>, Tie<C>)>,
+    // The id of each cell's node, to replace it when a cheaper one is pushed.
+    ids: HashMap<Point, u64>,
+    open: BTreeSet<(C, u64)>,
+    focal: BTreeSet<(C, Tie<C>, u64)>,
+}
+
+impl<C: Cost> FocalList<C> {
+    pub fn new(weight: f64, tie_break: TieBreak) -> FocalList<C> {
+        FocalList {
+            weight,
+            tie_break,
+            seq: 0,
+            threshold: None,
+            nodes: HashMap::new(),
+            ids: HashMap::new(),
+            open: BTreeSet::new(),
+            focal: BTreeSet::new(),
+        }
+    }
+
+    /// Adds `node`, replacing the node queued for its cell if it is cheaper
+    /// and dropping it otherwise.
+    pub fn push(&mut self, node: Node<C>) {
+        if let Some(&old) = self.ids.get(&node.position) {
+            let (queued, tie) = self.nodes[&old];
+
+            if queued.g <= node.g {
+                return;
+            }
+
+            self.open.remove(&(queued.f, old));
+            self.focal.remove(&(queued.h, tie, old));
+            self.nodes.remove(&old);
+        }
+
+        let id = self.seq;
+        let tie = self.tie_break.key(&node, id);
+        self.seq += 1;
+
+        self.nodes.insert(id, (node, tie));
+        self.ids.insert(node.position, id);
+        self.open.insert((node.f, id));
+
+        if self.threshold.is_some_and(|threshold| node.f <= threshold) {
+            self.focal.insert((node.h, tie, id));
+        }
+
+        self.refresh();
+    }
+
+    pub fn pop(&mut self) -> Option<Node<C>> {
+        let (_, _, id) = self.focal.pop_first()?;
+        let (node, _) = self.nodes.remove(&id)?;
+
+        self.open.remove(&(node.f, id));
+        self.ids.remove(&node.position);
+        self.refresh();
+
+        Some(node)
+    }
+
+    pub fn f_min(&self) -> Option<C> {
+        self.open.first().map(|(f, _)| *f)
+    }
+
+    fn threshold_for(&self, f_min: C) -> C {
+        C::floor(self.weight * f_min.to_f64())
+    }
+
+    /// Admits nodes into focal when f_min rises. Nodes admitted under an
+    /// earlier, higher threshold stay, which only changes expansion order.
+    fn refresh(&mut self) {
+        let threshold = match self.f_min() {
+            Some(f) => self.threshold_for(f),
+            None => return,
+        };
+
+        let lower = match self.threshold {
+            Some(old) if old >= threshold => None,
+            Some(old) => Some(Excluded((old, u64::MAX))),
+            None => Some(Unbounded),
+        };
+
+        if let Some(lower) = lower {
+            for (_, id) in self.open.range((lower, Included((threshold, u64::MAX)))) {
+                let (node, tie) = self.nodes[id];
+
+                self.focal.insert((node.h, tie, *id));
+            }
+        }
+
+        self.threshold = Some(threshold);
+    }
+}
@@ -0,0 +1,47 @@
+use std::{
+    collections::{HashMap, hash_map::DefaultHasher},
+    hash::{Hash, Hasher},
+    sync::Mutex
+};
+use super::{structs::Point, cost::Cost, helpers};
+
+/// Parent pointers shared by the threads of a distributed search. Sharded
+/// by the same point hash used to pick a node's owner, so with one shard
+/// per thread each thread only ever locks its own shard for writing.
+pub struct ParentTable {
+    shards: Vec<Mutex<HashMap<Point, Point>>>,
+}
+
+impl ParentTable {
+    pub fn new(shards: usize) -> ParentTable {
+        ParentTable { shards: (0..shards.max(1)).map(|_| Mutex::new(HashMap::new())).collect() }
+    }
+
+    fn shard(&self, point: &Point) -> &Mutex<HashMap<Point, Point>> {
+        let mut state = DefaultHasher::new();
+
+        point.hash(&mut state);
+        &self.shards[(state.finish() % self.shards.len() as u64) as usize]
+    }
+
+    pub fn insert(&self, point: Point, parent: Point) {
+        self.shard(&point).lock().unwrap().insert(point, parent);
+    }
+
+    pub fn get(&self, point: Point) -> Option<Point> {
+        self.shard(&point).lock().unwrap().get(&point).copied()
+    }
+
+    /// Path from the start to `end` and what it costs, a step between
+    /// neighbouring cells costing 1. Parents only ever change to ones with a
+    /// lower g, so the walk cannot cycle even while threads keep writing, but
+    /// it can cost less than the g `end` was reached with. Jumps between
+    /// parents are filled in cell by cell.
+    pub fn path<C: Cost>(&self, end: Point, limit: usize) -> (C, Vec<Point>) {
+        let points = helpers::trace_path(end, limit, |point| self.get(point));
+        let cost = points.windows(2)
+            .fold(C::ZERO, |cost, pair| cost + C::from_u32(((pair[1].x - pair[0].x).abs() + (pair[1].y - pair[0].y).abs()) as u32));
+
+        (cost, helpers::fill_path(points))
+    }
+}
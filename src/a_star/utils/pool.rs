@@ -0,0 +1,151 @@
+use crossbeam::channel::{Sender, Receiver, bounded, unbounded};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex, MutexGuard},
+    thread::{self, JoinHandle}
+};
+use super::{affinity::{self, Pinning}, cell_table::GridTable, cost::Cost};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// One thread of a `Pool`, running the jobs sent to it in order.
+struct Worker {
+    jobs: Option<Sender<Job>>,
+    handle: Option<JoinHandle<()>>,
+    // The CPU it pinned itself to when it started, if any.
+    cpu: Option<usize>,
+}
+
+/// What a search leasing a `Pool` gets to use.
+pub struct Leased {
+    workers: Vec<Worker>,
+    // Kept between searches and reset for each, as long as the map is the same size.
+    cells: Option<Arc<GridTable>>,
+}
+
+/// Worker threads kept between searches, so a search on many threads does
+/// not start and stop them for every query. Searches take turns: each holds
+/// the whole pool until its workers are done, since workers of the same
+/// search wait on each other and two searches sharing threads could stall.
+pub struct Pool {
+    leased: Mutex<Leased>,
+}
+
+impl Pool {
+    /// Starts `threads` workers, each pinned to its CPU in `pinning`'s plan.
+    pub fn new(threads: usize, pinning: Option<Pinning>) -> Pool {
+        let plan = pinning.map_or_else(Vec::new, |pinning| pinning.plan(threads));
+        let workers = (0..threads).map(|i| {
+            let (jobs, queue) = unbounded::<Job>();
+            let (pinned, pinned_to) = bounded(1);
+            let target = plan.get(i).copied();
+
+            let handle = thread::spawn(move || {
+                pinned.send(target.filter(|cpu| affinity::pin(*cpu))).ok();
+
+                for job in queue {
+                    job();
+                }
+            });
+
+            Worker { jobs: Some(jobs), handle: Some(handle), cpu: pinned_to.recv().unwrap_or(None) }
+        }).collect();
+
+        Pool { leased: Mutex::new(Leased { workers, cells: None }) }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.leased.lock().unwrap().workers.len()
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        let leased = self.leased.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        // Hanging up ends each worker's loop once its queue is empty.
+        for worker in leased.workers.iter_mut() {
+            worker.jobs.take();
+        }
+
+        for worker in leased.workers.iter_mut() {
+            if let Some(handle) = worker.handle.take() {
+                handle.join().ok();
+            }
+        }
+    }
+}
+
+/// The threads one search runs its workers on: a pool it holds for the
+/// whole search, or fresh threads started for it alone.
+pub enum Workers<'a> {
+    Pooled(MutexGuard<'a, Leased>),
+    /// The CPU plan fresh threads pin themselves to.
+    Fresh(Vec<usize>),
+}
+
+impl<'a> Workers<'a> {
+    /// Waits for `pool`, if there is one, otherwise plans fresh threads.
+    /// A pool's workers stay pinned as it was built, so `pinning` only
+    /// applies to fresh threads.
+    pub fn new(pool: Option<&'a Pool>, pinning: Option<Pinning>, threads: usize) -> Workers<'a> {
+        match pool {
+            Some(pool) => Workers::Pooled(pool.leased.lock().unwrap_or_else(|poisoned| poisoned.into_inner())),
+            None => Workers::Fresh(pinning.map_or_else(Vec::new, |pinning| pinning.plan(threads))),
+        }
+    }
+
+    /// Runs `work` as worker `i`. Workers past the end of a pool get fresh
+    /// threads, so every worker of a search always runs at once.
+    pub fn spawn<F, T>(&self, i: usize, work: F) -> Task<T>
+        where F: FnOnce() -> T + Send + 'static, T: Send + 'static {
+        match self {
+            Workers::Pooled(leased) if i < leased.workers.len() => {
+                let worker = &leased.workers[i];
+                let (result, receiver) = bounded(1);
+                let cpu = worker.cpu;
+
+                worker.jobs.as_ref().expect("Worker hung up").send(Box::new(move || {
+                    result.send(panic::catch_unwind(AssertUnwindSafe(work)).map(|value| (cpu, value))).ok();
+                })).expect("Worker hung up");
+
+                Task::Pooled(receiver)
+            },
+            Workers::Pooled(_) => Task::Fresh(thread::spawn(move || (None, work()))),
+            Workers::Fresh(plan) => Task::Fresh(affinity::spawn(plan, i, work)),
+        }
+    }
+
+    /// A cleared grid table for `graph` with costs of type `C`, if it can
+    /// have one. A pool keeps its table for the next search on the same map.
+    pub fn cells<C: Cost>(&mut self, graph: &[Vec<char>]) -> Option<Arc<GridTable>> {
+        match self {
+            Workers::Pooled(_) if !C::INTEGER => None,
+            Workers::Pooled(leased) => {
+                match &leased.cells {
+                    Some(cells) if cells.fits(graph) => cells.reset(),
+                    _ => leased.cells = GridTable::for_graph::<C>(graph).map(Arc::new),
+                }
+
+                leased.cells.clone()
+            },
+            Workers::Fresh(_) => GridTable::for_graph::<C>(graph).map(Arc::new),
+        }
+    }
+}
+
+/// A worker of a search that has been started, to be waited on.
+pub enum Task<T> {
+    Pooled(Receiver<thread::Result<(Option<usize>, T)>>),
+    Fresh(JoinHandle<(Option<usize>, T)>),
+}
+
+impl<T> Task<T> {
+    /// Waits for the worker, giving the CPU it ran pinned to and its result.
+    pub fn join(self) -> thread::Result<(Option<usize>, T)> {
+        match self {
+            Task::Pooled(receiver) => receiver.recv().expect("Worker hung up"),
+            Task::Fresh(handle) => handle.join(),
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::{fmt, iter::Sum, ops::AddAssign, time::Duration};
+
+/// Work done by one thread of a search. Summing the threads of a parallel
+/// search and comparing with sequential A* gives its search overhead.
+#[derive(PartialEq, Eq, Copy, Clone, Default, Debug)]
+pub struct SearchStats {
+    /// Nodes taken off the open list and expanded.
+    pub expansions: u64,
+    /// Successors produced by those expansions.
+    pub generated: u64,
+    /// Successors dropped because they were already known at no greater cost.
+    pub duplicates: u64,
+    /// Expanded nodes put back on the open list after a cheaper path turned up.
+    pub reopened: u64,
+    /// Heuristic evaluations, not counting the one for the start node.
+    pub evaluations: u64,
+    /// Nodes taken from other threads' open lists by work stealing.
+    pub stolen: u64,
+    /// Steals that took any nodes, each one batch of up to a few nodes.
+    pub steals: u64,
+    /// Entries held in a fixed-size table when the search ended, such as the
+    /// transposition table of IDA*.
+    pub table_entries: u64,
+}
+
+impl AddAssign for SearchStats {
+    fn add_assign(&mut self, other: SearchStats) {
+        self.expansions += other.expansions;
+        self.generated += other.generated;
+        self.duplicates += other.duplicates;
+        self.reopened += other.reopened;
+        self.evaluations += other.evaluations;
+        self.stolen += other.stolen;
+        self.steals += other.steals;
+        self.table_entries += other.table_entries;
+    }
+}
+
+impl<'a> Sum<&'a SearchStats> for SearchStats {
+    fn sum<I: Iterator<Item = &'a SearchStats>>(iter: I) -> SearchStats {
+        iter.fold(SearchStats::default(), |mut total, stats| {
+            total += *stats;
+            total
+        })
+    }
+}
+
+impl fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} expanded, {} generated, {} duplicates, {} reopened, {} heuristic evaluations, {} stolen in {} steals, {} table entries",
+               self.expansions, self.generated, self.duplicates, self.reopened, self.evaluations, self.stolen, self.steals, self.table_entries)
+    }
+}
+
+/// Message traffic and synchronisation of one thread of HDA* or DPA*.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+pub struct CommStats {
+    /// This thread's number, its own entry in `sent`.
+    pub thread: usize,
+    /// Nodes sent to each thread, by thread number.
+    pub sent: Vec<u64>,
+    pub received: u64,
+    /// Messages sent. HDA* groups nodes into batches, so this can be far
+    /// fewer than the nodes in `sent`.
+    pub batches: u64,
+    /// Most messages found waiting in this thread's channel at once.
+    pub queue_high_water: usize,
+    /// Time spent idle, waiting for a message.
+    pub blocked: Duration,
+    /// Iterations that expanded nothing because no node left here could
+    /// improve the incumbent.
+    pub idle_iterations: u64,
+}
+
+impl CommStats {
+    pub fn new(thread: usize, threads: usize) -> CommStats {
+        CommStats { thread, sent: vec![0; threads], ..CommStats::default() }
+    }
+
+    /// Messages this thread sent to itself. These still go through its
+    /// channel, but never leave the core.
+    pub fn self_sends(&self) -> u64 {
+        self.sent[self.thread]
+    }
+
+    pub fn remote_sends(&self) -> u64 {
+        self.sent.iter().sum::<u64>() - self.self_sends()
+    }
+}
+
+impl fmt::Display for CommStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "sent {:?} by thread ({} to self, {} remote) in {} messages, {} received, queue peaked at {}, {:.3}s waiting idle, {} idle iterations",
+               self.sent, self.self_sends(), self.remote_sends(), self.batches, self.received, self.queue_high_water,
+               self.blocked.as_secs_f64(), self.idle_iterations)
+    }
+}
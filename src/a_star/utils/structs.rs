@@ -1,35 +1,27 @@
 use std::default::Default;
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
 use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crossbeam::channel::Sender;
+use super::{affinity::Pinning, pool::Pool, limits::{Limits, StopReason}, successors::Successors, heuristics::{Heuristic, Euclidean, mix}, stats::{SearchStats, CommStats}, cost::Cost};
 
 /// Goal node path and cost of path.
 #[derive(Copy, Clone)]
-pub struct Incumbent {
-    pub node: Node,
-    pub cost: i128
+pub struct Incumbent<C: Cost> {
+    pub node: Node<C>,
+    pub cost: C
 }
 
-impl Incumbent {
-    pub fn new(node: Node, cost: i128) -> Incumbent {
+impl<C: Cost> Incumbent<C> {
+    pub fn new(node: Node<C>, cost: C) -> Incumbent<C> {
         Incumbent { node, cost }
     }
 }
 
 /// Container for transmitting messages.
 #[derive(PartialEq, Eq, Copy, Clone, Debug)]
-pub struct Buffer (pub Node, pub i128, pub Node);
-
-impl Ord for Buffer {
-    fn cmp(&self, other: &Self) -> Ordering  {
-        other.0.f.cmp(&self.0.f)
-    }
-}
-
-impl PartialOrd for Buffer {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
+pub struct Buffer<C: Cost> (pub Node<C>, pub C, pub Node<C>);
 
 /// Point struct for node coordinates.
 #[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
@@ -46,59 +38,241 @@ impl Default for Point {
 
 /// Node struct for nodes in graph.
 #[derive(Clone, Copy, Default, Debug)]
-pub struct Node {
+pub struct Node<C: Cost> {
     pub position: Point,
-    pub f: i128,
-    pub g: i128,
-    pub h: i128,
+    pub f: C,
+    pub g: C,
+    pub h: C,
     pub parent: Point,
 }
 
-impl Node {
-    pub fn new(x: i32, y: i32, f: i128, g: i128, h: i128, parent: Point) -> Node {
+impl<C: Cost> Node<C> {
+    pub fn new(x: i32, y: i32, f: C, g: C, h: C, parent: Point) -> Node<C> {
         Node { position: Point { x, y }, f, g, h, parent }
     }
+
+    /// A node at `point` with no costs or parent yet.
+    pub fn at(point: Point) -> Node<C> {
+        Node { position: point, ..Node::default() }
+    }
 }
 
-impl Ord for Node {
+impl<C: Cost> Ord for Node<C> {
     fn cmp(&self, other: &Self) -> Ordering  {
         other.f.cmp(&self.f)
     }
 }
 
-impl PartialOrd for Node {
+impl<C: Cost> PartialOrd for Node<C> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl Eq for Node {}
+impl<C: Cost> Eq for Node<C> {}
 
-impl PartialEq for Node {
+impl<C: Cost> PartialEq for Node<C> {
     fn eq(&self, other: &Self) -> bool  {
         self.position == other.position
     }
 }
 
-impl Hash for Node {
+impl<C: Cost> Hash for Node<C> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.position.hash(state);
     }
 }
 
-/// Herustic enum
-#[derive(PartialEq, Copy, Clone)]
-pub enum HeurType {
-    ManhattanDist,
-    EuclideanDist,
-    Expensive,
-    NonAdmissible,
-    ExpensiveNonAdmissible
+/// Node ordering used by the open lists, and the suboptimality bound it works to.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum SearchMode {
+    /// Expands by f = g + w·h. A weight of 1 is plain A*.
+    Weighted(f64),
+    /// Expands the lowest h among nodes with f = g + h within w·f_min.
+    Focal(f64),
+}
+
+impl SearchMode {
+    /// Suboptimality factor the returned cost is guaranteed to be within.
+    pub fn weight(&self) -> f64 {
+        match self {
+            SearchMode::Weighted(w) | SearchMode::Focal(w) => *w,
+        }
+    }
+
+    /// Ordering value stored in `Node::f`.
+    pub fn f<C: Cost>(&self, g: C, h: C) -> C {
+        match self {
+            SearchMode::Weighted(w) if *w == 1.0 => g + h,
+            SearchMode::Weighted(w) => g + C::floor(*w * h.to_f64()),
+            SearchMode::Focal(_) => g + h,
+        }
+    }
+}
+
+impl Default for SearchMode {
+    fn default() -> SearchMode {
+        SearchMode::Weighted(1.0)
+    }
+}
+
+/// How the open lists order nodes of equal f. Grids have many of them, and
+/// which goes first can change the number of expansions several-fold.
+#[derive(PartialEq, Eq, Copy, Clone, Default, Debug)]
+pub enum TieBreak {
+    /// Deepest first, so the search runs on towards the goal along one of
+    /// several equally good paths instead of widening out across all of them.
+    #[default]
+    HighG,
+    LowH,
+    /// Most recently pushed first.
+    Lifo,
+    Fifo,
+    /// A shuffle fixed by the seed and the cell, the same on every thread.
+    Random(u64),
+}
+
+impl TieBreak {
+    /// Secondary key for `node`, the `seq`th node pushed to its list. Lower
+    /// keys go first among nodes of equal f.
+    pub fn key<C: Cost>(&self, node: &Node<C>, seq: u64) -> Tie<C> {
+        match self {
+            TieBreak::HighG => Tie::Greater(Reverse(node.g)),
+            TieBreak::LowH => Tie::Less(node.h),
+            TieBreak::Lifo => Tie::Order(u64::MAX - seq),
+            TieBreak::Fifo => Tie::Order(seq),
+            TieBreak::Random(seed) => Tie::Order(mix(mix(*seed) ^ ((node.position.x as u32 as u64) << 32 | node.position.y as u32 as u64))),
+        }
+    }
+}
+
+/// A `TieBreak` key. One list only ever holds keys of one kind.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug)]
+pub enum Tie<C: Cost> {
+    Less(C),
+    Greater(Reverse<C>),
+    Order(u64),
+}
+
+/// Incumbent reported by an anytime search as soon as it is found. The path
+/// follows the parents known at that moment and `cost` is the incumbent's.
+#[derive(Clone, Debug)]
+pub struct Improvement {
+    /// The cost as a real number, whatever type the search used.
+    pub cost: f64,
+    pub path: Vec<Point>,
+    pub elapsed: Duration,
+}
+
+/// Anytime search settings. The search keeps going after the first goal,
+/// pruning on g + h against the incumbent, until the open lists are
+/// exhausted or one of the search's `Limits` stops it.
+#[derive(Clone)]
+pub struct Anytime {
+    pub updates: Sender<Improvement>,
+    pub started: Instant,
+    // Cost of the last path sent, shared by every thread's copy.
+    best: Arc<Mutex<f64>>,
+}
+
+impl Anytime {
+    pub fn new(updates: Sender<Improvement>) -> Anytime {
+        Anytime { updates, started: Instant::now(), best: Arc::new(Mutex::new(f64::INFINITY)) }
+    }
+
+    /// Sends a new incumbent's path and cost, as the search counts it, if it
+    /// is cheaper than every one sent before. Ignores a receiver that has
+    /// gone away.
+    pub fn report<C: Cost>(&self, cost: C, path: Vec<Point>) {
+        let cost = cost.to_f64();
+        let mut best = self.best.lock().unwrap();
+
+        if cost < *best {
+            *best = cost;
+            self.updates.send(Improvement { cost, path, elapsed: self.started.elapsed() }).ok();
+        }
+    }
+}
+
+/// Best path found by a search, from start to goal, and why the search
+/// stopped. `cost` is `None` when no path was found. `stats` and `comm` have
+/// one entry per thread for the searches that keep them, and are empty
+/// otherwise.
+#[derive(Clone, Debug)]
+pub struct Solution<C: Cost> {
+    pub cost: Option<C>,
+    pub path: Vec<Point>,
+    pub reason: StopReason,
+    pub stats: Vec<SearchStats>,
+    pub comm: Vec<CommStats>,
+    /// CPU each worker ran pinned to, for the searches that pin them.
+    pub placement: Vec<Option<usize>>,
+}
+
+impl<C: Cost> Solution<C> {
+    pub fn new(cost: C, path: Vec<Point>, reason: StopReason) -> Solution<C> {
+        if cost == C::INFINITY {
+            Solution { cost: None, path: Vec::new(), reason, stats: Vec::new(), comm: Vec::new(), placement: Vec::new() }
+        }
+        else {
+            Solution { cost: Some(cost), path, reason, stats: Vec::new(), comm: Vec::new(), placement: Vec::new() }
+        }
+    }
+
+    pub fn with_stats(self, stats: Vec<SearchStats>) -> Solution<C> {
+        Solution { stats, ..self }
+    }
+
+    pub fn with_comm(self, comm: Vec<CommStats>) -> Solution<C> {
+        Solution { comm, ..self }
+    }
+
+    pub fn with_placement(self, placement: Vec<Option<usize>>) -> Solution<C> {
+        Solution { placement, ..self }
+    }
+}
+
+/// How HDA* threads group the nodes they send each other. A batch goes out
+/// once it holds `size` nodes or its oldest node has waited `wait`, and a
+/// thread sends everything it holds before going idle. A size of 1 sends
+/// each node on its own.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Batching {
+    pub size: usize,
+    pub wait: Duration,
+}
+
+impl Default for Batching {
+    fn default() -> Batching {
+        Batching { size: 32, wait: Duration::from_micros(200) }
+    }
 }
 
 /// Configuration struct for command line arguments
+#[derive(Clone)]
 pub struct Flags {
-    pub graph: Vec<Vec<char>>,
-    pub heur: HeurType,
-    pub threads: usize
+    /// The map, shared rather than copied between threads and queries.
+    pub graph: Arc<Vec<Vec<char>>>,
+    pub heur: Arc<dyn Heuristic>,
+    pub threads: usize,
+    pub mode: SearchMode,
+    pub anytime: Option<Anytime>,
+    pub limits: Limits,
+    /// Transposition table entries for IDA*, none keeps only the current path.
+    pub table_size: Option<usize>,
+    /// Successor generation for the searches that support it, A* and HDA*.
+    pub successors: Successors,
+    pub batching: Batching,
+    pub tie_break: TieBreak,
+    /// Pins the workers of HDA*, DPA* and KPBFS to CPUs, none leaves them to the OS.
+    pub pinning: Option<Pinning>,
+    /// Threads to run workers on instead of starting new ones, see `Solver`.
+    pub pool: Option<Arc<Pool>>,
+}
+
+impl Default for Flags {
+    fn default() -> Flags {
+        Flags { graph: Arc::new(Vec::new()), heur: Arc::new(Euclidean), threads: 4, mode: SearchMode::default(), anytime: None, limits: Limits::default(), table_size: None,
+                successors: Successors::Neighbors, batching: Batching::default(), tie_break: TieBreak::default(), pinning: None, pool: None }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,222 @@
+use std::{cmp, sync::Arc};
+use super::{structs::{Node, Point}, cost::Cost, helpers};
+
+// Order matches the neighbor order used everywhere else.
+const DIRECTIONS: [(i32, i32); 4] = [(0, 1), (-1, 0), (1, 0), (0, -1)];
+
+/// How a node's successors are generated.
+#[derive(Clone, Default)]
+pub enum Successors {
+    /// The four adjacent cells, each at a cost of 1.
+    #[default]
+    Neighbors,
+    /// Jump Point Search restricted to four-connected moves. Successors are
+    /// the next jump points in each unpruned direction, at the cost of the
+    /// straight run to them.
+    Jps,
+    /// Jump Point Search answered from precomputed jump distances. The table
+    /// does not depend on the goal, so one can be shared by any number of
+    /// searches over the same graph.
+    JpsPlus(Arc<JumpTable>),
+}
+
+impl Successors {
+    /// Successors of `node` and the cost of reaching each from it.
+    pub fn expand<C: Cost>(&self, graph: &[Vec<char>], node: &Node<C>, goal: Point) -> Vec<(Point, C)> {
+        let mut successors = Vec::with_capacity(DIRECTIONS.len());
+
+        match self {
+            Successors::Neighbors => {
+                for (x, y) in DIRECTIONS.iter() {
+                    if helpers::is_valid_neighbor(graph, node, *x, *y) {
+                        successors.push((Point { x: node.position.x + x, y: node.position.y + y }, C::from_u32(1)));
+                    }
+                }
+            },
+            Successors::Jps | Successors::JpsPlus(_) => {
+                for direction in pruned_directions(graph, node) {
+                    let jump_point = match self {
+                        Successors::JpsPlus(table) => table.jump(node.position, direction, goal),
+                        _ => jump(graph, node.position, direction, goal),
+                    };
+
+                    if let Some(point) = jump_point {
+                        successors.push((point, C::from_u32(distance(node.position, point))));
+                    }
+                }
+            },
+        }
+
+        successors
+    }
+}
+
+fn walkable(graph: &[Vec<char>], x: i32, y: i32) -> bool {
+    x >= 0 && y >= 0 && x < graph.len() as i32 && y < graph.len() as i32 && graph[x as usize][y as usize] != 'W'
+}
+
+fn distance(from: Point, to: Point) -> u32 {
+    ((from.x - to.x).abs() + (from.y - to.y).abs()) as u32
+}
+
+/// Directions worth searching from `node` given the direction it was reached
+/// in: straight on plus both turns. The start, which has no parent, searches
+/// all four.
+fn pruned_directions<C: Cost>(graph: &[Vec<char>], node: &Node<C>) -> Vec<(i32, i32)> {
+    let Point { x, y } = node.position;
+
+    if node.parent == Point::default() {
+        return DIRECTIONS.iter().copied().filter(|(dx, dy)| walkable(graph, x + dx, y + dy)).collect();
+    }
+
+    let (dx, dy) = ((x - node.parent.x).signum(), (y - node.parent.y).signum());
+    let candidates = if dx != 0 { [(0, -1), (0, 1), (dx, 0)] } else { [(-1, 0), (1, 0), (0, dy)] };
+
+    candidates.iter().copied().filter(|(cx, cy)| walkable(graph, x + cx, y + cy)).collect()
+}
+
+/// Whether a cell moved into along x has a neighbor that can only be reached
+/// optimally through it.
+fn forced_along_x(graph: &[Vec<char>], x: i32, y: i32, dx: i32) -> bool {
+    (walkable(graph, x, y - 1) && !walkable(graph, x - dx, y - 1))
+        || (walkable(graph, x, y + 1) && !walkable(graph, x - dx, y + 1))
+}
+
+/// Same as `forced_along_x` for moves along y.
+fn forced_along_y(graph: &[Vec<char>], x: i32, y: i32, dy: i32) -> bool {
+    (walkable(graph, x - 1, y) && !walkable(graph, x - 1, y - dy))
+        || (walkable(graph, x + 1, y) && !walkable(graph, x + 1, y - dy))
+}
+
+/// Walks from `from` in `direction` to the next jump point: the goal, a cell
+/// with a forced neighbor, or, moving along y, a cell from which a jump along
+/// x finds one. Runs along x never turn, which keeps this iterative.
+fn jump(graph: &[Vec<char>], from: Point, (dx, dy): (i32, i32), goal: Point) -> Option<Point> {
+    let Point { mut x, mut y } = from;
+
+    loop {
+        x += dx;
+        y += dy;
+
+        if !walkable(graph, x, y) {
+            return None;
+        }
+
+        let point = Point { x, y };
+
+        if point == goal {
+            return Some(point);
+        }
+
+        let jump_point = if dx != 0 {
+            forced_along_x(graph, x, y, dx)
+        }
+        else {
+            forced_along_y(graph, x, y, dy)
+                || jump(graph, point, (1, 0), goal).is_some()
+                || jump(graph, point, (-1, 0), goal).is_some()
+        };
+
+        if jump_point {
+            return Some(point);
+        }
+    }
+}
+
+/// Jump distances for every cell and direction, computed once per graph.
+/// Only the goal-independent jump points are stored; jumps that stop at or
+/// in line with the goal are worked out from the free run lengths at query time.
+pub struct JumpTable {
+    size: usize,
+    // Steps to the next goal-independent jump point, 0 if a wall comes first.
+    jumps: Vec<[u32; 4]>,
+    // Free cells before the next wall or the edge of the graph.
+    runs: Vec<[u32; 4]>,
+}
+
+impl JumpTable {
+    pub fn new(graph: &[Vec<char>]) -> JumpTable {
+        let size = graph.len();
+        let mut table = JumpTable { size, jumps: vec![[0; 4]; size * size], runs: vec![[0; 4]; size * size] };
+
+        // Jumps along y stop wherever a jump along x would, so x goes first.
+        for d in [1, 2, 0, 3] {
+            let (dx, dy) = DIRECTIONS[d];
+
+            // Visit cells so the one ahead in direction d is always done first.
+            for i in 0..size {
+                for j in 0..size {
+                    let x = if dx > 0 { size - 1 - i } else { i } as i32;
+                    let y = if dy > 0 { size - 1 - j } else { j } as i32;
+                    let (next_x, next_y) = (x + dx, y + dy);
+
+                    if !walkable(graph, x, y) || !walkable(graph, next_x, next_y) {
+                        continue;
+                    }
+
+                    let next = table.index(next_x, next_y);
+                    let stops = if dx != 0 {
+                        forced_along_x(graph, next_x, next_y, dx)
+                    }
+                    else {
+                        forced_along_y(graph, next_x, next_y, dy) || table.jumps[next][1] > 0 || table.jumps[next][2] > 0
+                    };
+
+                    let here = table.index(x, y);
+                    table.runs[here][d] = table.runs[next][d] + 1;
+                    table.jumps[here][d] = match (stops, table.jumps[next][d]) {
+                        (true, _) => 1,
+                        (false, 0) => 0,
+                        (false, ahead) => ahead + 1,
+                    };
+                }
+            }
+        }
+
+        table
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        x as usize * self.size + y as usize
+    }
+
+    /// Same answer as walking the graph with `jump`, in constant time.
+    fn jump(&self, from: Point, (dx, dy): (i32, i32), goal: Point) -> Option<Point> {
+        let d = DIRECTIONS.iter().position(|&direction| direction == (dx, dy))?;
+        let here = self.index(from.x, from.y);
+        let run = self.runs[here][d] as i32;
+        let mut steps = match self.jumps[here][d] {
+            0 => i32::MAX,
+            jump => jump as i32,
+        };
+
+        // Steps needed to draw level with the goal, if it is ahead at all.
+        let level = if dx != 0 {
+            if from.y == goal.y { (goal.x - from.x) * dx } else { 0 }
+        }
+        else {
+            (goal.y - from.y) * dy
+        };
+
+        if level >= 1 && level <= run {
+            let point = Point { x: from.x + dx * level, y: from.y + dy * level };
+            let across = goal.x - point.x;
+
+            // Moving along y, a cell in the goal's column stops the jump when
+            // the goal can be reached from it straight along x.
+            let reaches = across == 0 || {
+                let side = if across > 0 { 2 } else { 1 };
+                across.abs() <= self.runs[self.index(point.x, point.y)][side] as i32
+            };
+
+            if reaches {
+                steps = cmp::min(steps, level);
+            }
+        }
+
+        match steps {
+            i32::MAX => None,
+            steps => Some(Point { x: from.x + dx * steps, y: from.y + dy * steps }),
+        }
+    }
+}
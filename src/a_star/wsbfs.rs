@@ -0,0 +1,276 @@
+use ::atomic::Atomic;
+use crossbeam::utils::Backoff;
+use std::{
+    thread,
+    time::Duration,
+    sync::{Arc, Mutex},
+    sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering}
+};
+use super::utils::{
+    structs::{Incumbent, Node, Point, Flags, Solution},
+    cost::Cost,
+    open_list::OpenList,
+    closed::ClosedTable,
+    pool::Workers,
+    limits::Budget,
+    stats::SearchStats,
+    helpers
+};
+
+// Most nodes taken from another thread in one steal. Taking several keeps a
+// thief busy for a while, but each is one the victim would have expanded next.
+const STEAL: usize = 4;
+// How long an idle thread sleeps between looks at the other lists once it
+// has backed off as far as spinning and yielding go.
+const IDLE_SLEEP: Duration = Duration::from_micros(50);
+
+/// State shared by the threads of a work-stealing search.
+struct Shared<C: Cost> {
+    /// One open list per thread. A thread pushes and pops its own, and only
+    /// locks another's to steal from it.
+    opens: Vec<Mutex<OpenList<C>>>,
+    closed: ClosedTable<C>,
+    incumbent: Atomic<Incumbent<C>>,
+    /// Threads holding nodes taken off an open list whose successors are
+    /// not pushed yet.
+    busy: AtomicUsize,
+    /// Bumped each time a thread takes nodes, so a termination check can tell
+    /// a thread started and finished while it was looking at the lists.
+    started: AtomicU64,
+    done: AtomicBool,
+    budget: Budget,
+    limit: usize,
+}
+
+impl<C: Cost> Shared<C> {
+    /// Takes up to `count` nodes worth expanding from `thread`'s open list.
+    /// Marked busy before the first pop, under the list's lock, so a
+    /// termination check that misses the nodes in the list sees the thread.
+    fn take(&self, thread: usize, count: usize, flags: &Flags) -> Vec<Node<C>> {
+        let mut open = self.opens[thread].lock().unwrap();
+        let mut nodes = Vec::new();
+
+        if !has_work(&open, &self.incumbent, flags) {
+            return nodes;
+        }
+
+        self.busy.fetch_add(1, Ordering::SeqCst);
+        self.started.fetch_add(1, Ordering::SeqCst);
+
+        while nodes.len() < count && has_work(&open, &self.incumbent, flags) {
+            nodes.push(open.pop().unwrap());
+        }
+
+        nodes
+    }
+
+    /// The other thread whose open list has the best node, if any has one
+    /// worth expanding.
+    fn victim(&self, thief: usize, flags: &Flags) -> Option<usize> {
+        (0..self.opens.len())
+            .filter(|thread| *thread != thief)
+            .filter_map(|thread| {
+                let open = self.opens[thread].lock().unwrap();
+
+                if has_work(&open, &self.incumbent, flags) { open.bound().map(|bound| (bound, thread)) } else { None }
+            })
+            .min()
+            .map(|(_, thread)| thread)
+    }
+
+    /// Whether the search is over: no thread is busy and no open list has a
+    /// node worth expanding. Nodes only move between lists while a thread is
+    /// busy, so if none was busy before or after the scan and none started
+    /// during it, the scan saw every list as it stood.
+    fn terminated(&self, flags: &Flags) -> bool {
+        let started = self.started.load(Ordering::SeqCst);
+
+        if self.busy.load(Ordering::SeqCst) != 0 {
+            return false;
+        }
+
+        let idle = self.opens.iter().all(|open| !has_work(&open.lock().unwrap(), &self.incumbent, flags));
+
+        idle && self.busy.load(Ordering::SeqCst) == 0 && self.started.load(Ordering::SeqCst) == started
+    }
+}
+
+/// Whether the open list still holds a node that could improve the incumbent.
+/// Anytime runs order by weighted f but prune on g + h, which the bound cannot
+/// tell, so any node counts and stale ones are dropped as they are popped.
+fn has_work<C: Cost>(open: &OpenList<C>, incumbent: &Atomic<Incumbent<C>>, flags: &Flags) -> bool {
+    if flags.anytime.is_some() {
+        return !open.is_empty();
+    }
+
+    open.bound().is_some_and(|bound| bound < incumbent.load(Ordering::SeqCst).cost)
+}
+
+pub fn setup<C: Cost>(start_point: Point, end_point: Point, flags: Flags) -> Solution<C> {
+    let Flags { heur, graph, threads: thread_cnt, mode, anytime, limits, successors, tie_break, pool, .. } = flags;
+    let workers = Workers::new(pool.as_deref(), None, thread_cnt);
+    let mut threads = Vec::with_capacity(thread_cnt);
+
+    let mut start = Node::at(start_point);
+    let end = Node::at(end_point);
+    start.h = helpers::heuristic(start, end, &*heur);
+    start.f = mode.f(start.g, start.h);
+
+    let shared = Arc::new(Shared {
+        opens: (0..thread_cnt).map(|_| Mutex::new(OpenList::new(mode, tie_break))).collect(),
+        closed: ClosedTable::new(thread_cnt * 4),
+        incumbent: Atomic::new(Incumbent::new(start, C::INFINITY)),
+        busy: AtomicUsize::new(0),
+        started: AtomicU64::new(0),
+        done: AtomicBool::new(false),
+        budget: Budget::new(limits.clone()),
+        limit: graph.len() * graph.len(),
+    });
+
+    // The other threads start out idle and steal their first nodes.
+    shared.closed.improve(start.position, start.g, Point::default());
+    shared.opens[0].lock().unwrap().push(start);
+
+    for i in 0..thread_cnt {
+        let shared = shared.clone();
+        let flags = Flags { graph: graph.clone(), heur: heur.clone(), threads: thread_cnt, mode, anytime: anytime.clone(), limits: limits.clone(),
+                            successors: successors.clone(), tie_break, ..Flags::default() };
+
+        threads.push(workers.spawn(i, move || {
+            search(i, end, shared, flags)
+        }))
+    }
+
+    // Final answer is outputted once all threads are done.
+    let stats = threads.into_iter().map(|thread| thread.join().expect("Panic").1).collect();
+
+    let final_incumbent = shared.incumbent.load(Ordering::SeqCst);
+
+    Solution::new(final_incumbent.cost, shared.closed.path(final_incumbent.node.position, shared.limit),
+                  shared.budget.reason()).with_stats(stats)
+}
+
+fn search<C: Cost>(thread_num: usize, goal_node: Node<C>, shared: Arc<Shared<C>>, flags: Flags) -> SearchStats {
+    let mut stats = SearchStats::default();
+    let backoff = Backoff::new();
+
+    loop {
+        if shared.done.load(Ordering::SeqCst) {
+            return stats;
+        }
+
+        if shared.budget.exceeded() {
+            shared.done.store(true, Ordering::SeqCst);
+            return stats;
+        }
+
+        let mut nodes = shared.take(thread_num, 1, &flags);
+
+        // Out of work here, so take the best nodes from whichever thread has
+        // the best one. They go on this thread's list and are expanded from
+        // there, in order with anything they generate.
+        if nodes.is_empty() {
+            if let Some(victim) = shared.victim(thread_num, &flags) {
+                let stolen = shared.take(victim, STEAL, &flags);
+
+                if !stolen.is_empty() {
+                    let mut open = shared.opens[thread_num].lock().unwrap();
+
+                    stats.stolen += stolen.len() as u64;
+                    stats.steals += 1;
+                    for node in stolen {
+                        open.push(node);
+                    }
+                    drop(open);
+                    shared.busy.fetch_sub(1, Ordering::SeqCst);
+                    backoff.reset();
+                    continue;
+                }
+            }
+
+            if shared.terminated(&flags) {
+                shared.done.store(true, Ordering::SeqCst);
+                continue;
+            }
+
+            // Looking for work locks every list, so an idle thread waits longer
+            // each time it finds none rather than keep the busy ones waiting.
+            if backoff.is_completed() {
+                thread::sleep(IDLE_SLEEP);
+            }
+            else {
+                backoff.snooze();
+            }
+            continue;
+        }
+
+        backoff.reset();
+
+        let node = nodes.pop().unwrap();
+
+        expand(node, thread_num, goal_node, &shared, &flags, &mut stats);
+        shared.busy.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn expand<C: Cost>(node: Node<C>, thread_num: usize, goal_node: Node<C>, shared: &Shared<C>, flags: &Flags, stats: &mut SearchStats) {
+    let incumbent = &shared.incumbent;
+
+    if flags.anytime.is_some() && node.g + node.h >= incumbent.load(Ordering::SeqCst).cost {
+        return;
+    }
+
+    // A cheaper copy was generated after this one was pushed.
+    if !shared.closed.expand(node.position, node.g) {
+        return;
+    }
+
+    shared.budget.expanded();
+    stats.expansions += 1;
+
+    // If this is the goal, store it as the incumbent if it improves on it.
+    if node == goal_node {
+        let mut current = incumbent.load(Ordering::SeqCst);
+
+        while node.g < current.cost {
+            match incumbent.compare_exchange(current, Incumbent::new(node, node.g), Ordering::SeqCst, Ordering::SeqCst) {
+                Ok(_) => {
+                    if let Some(anytime) = &flags.anytime {
+                        anytime.report(node.g, shared.closed.path(node.position, shared.limit));
+                    }
+                    break;
+                },
+                Err(actual) => current = actual,
+            }
+        }
+        return;
+    }
+
+    let mut successors = Vec::new();
+
+    for (position, cost) in flags.successors.expand(&flags.graph, &node, goal_node.position) {
+        let mut n_prime = Node::new(position.x, position.y, C::ZERO, node.g + cost, C::ZERO, node.position);
+        stats.generated += 1;
+
+        match shared.closed.improve(n_prime.position, n_prime.g, node.position) {
+            None => {
+                stats.duplicates += 1;
+                continue;
+            },
+            Some(true) => stats.reopened += 1,
+            Some(false) => (),
+        }
+
+        n_prime.h = helpers::heuristic(n_prime, goal_node, &*flags.heur);
+        n_prime.f = flags.mode.f(n_prime.g, n_prime.h);
+        stats.evaluations += 1;
+        shared.budget.stored();
+        successors.push(n_prime);
+    }
+
+    let mut open = shared.opens[thread_num].lock().unwrap();
+
+    for n_prime in successors {
+        open.push(n_prime);
+    }
+}
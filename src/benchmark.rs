@@ -1,25 +1,20 @@
+use std::sync::Arc;
 use criterion::{criterion_group, criterion_main, Criterion};
-mod a_star;
-use a_star::{
-    utils::structs::{HeurType, Flags},
+use parallel_astar_rust::a_star::{
+    utils::structs::Flags,
+    utils::heuristics::{Registry, Euclidean},
+    utils::limits::Limits,
+    astar,
     hda,
+    hpa::{HpaIndex, DEFAULT_CLUSTER_SIZE},
     dpa,
     kpbfs,
+    wsbfs,
+    bidir,
+    solver::{Solver, Algorithm},
     utils::helpers::{parse_graph}
 };
 
-fn string_from_heur(heur: HeurType) -> String {
-    let heur_str = match heur {
-        HeurType::EuclideanDist => "euclidean",
-        HeurType::ManhattanDist => "manhattan",
-        HeurType::Expensive => "expensive",
-        HeurType::NonAdmissible => "nonadmissible",
-        HeurType::ExpensiveNonAdmissible => "expnon",
-    };
-
-    return heur_str.to_string();
-}
-
 /*
 To run and filter to a certain group of benchmarks simply run:
 
@@ -36,18 +31,15 @@ fn criterion_benchmark(c: &mut Criterion) {
     // algo_#t_heurtype
     // Threads: 1, 2, 4, 8, 16
     let thread_cnts: [usize; 5] = [1, 2, 4, 8, 16];
-    // heurtype: all
-    let heurs: [HeurType; 5] = [
-        HeurType::ManhattanDist,
-        HeurType::EuclideanDist,
-        HeurType::Expensive,
-        HeurType::NonAdmissible,
-        HeurType::ExpensiveNonAdmissible
-    ];
+    // heurtype: all built-ins
+    let registry = Registry::default();
 
-    let algo_type: [String; 3] = ["kpbfs".to_string(), "dpa".to_string(), "hda".to_string()];    
+    let algo_type: [String; 6] = ["astar".to_string(), "kpbfs".to_string(), "wsbfs".to_string(), "dpa".to_string(), "hda".to_string(), "bidir".to_string()];    
 
     let input = "medium1.in";
+    let heurs: Vec<_> = registry.names().into_iter()
+        .map(|name| (name, registry.build(name, &parse_graph(Some(input)).0).unwrap()))
+        .collect();
 
     // Reduce the sample size for PA* algos.
     let mut group = c.benchmark_group("pa");
@@ -55,30 +47,42 @@ fn criterion_benchmark(c: &mut Criterion) {
     group.sample_size(10);
 
     for cnt in thread_cnts.iter() {
-        for heur_type in heurs.iter() {
+        for (name, heur_type) in heurs.iter() {
             for algo in algo_type.iter() {
     
-                let format = format!("{}_{}t_{}", *algo, (*cnt).to_string(), string_from_heur(*heur_type));
+                let format = format!("{}_{}t_{}", *algo, *cnt, name);
 
                 // Graph nor flags is copyable
                 let (_, start, end) = parse_graph(Some(input));
 
                 match algo.as_ref() {
+                    "astar" => {
+                        group.bench_function(&format, |b| b.iter(|| astar::setup::<i128>(start, end,
+                            Flags { graph: Arc::new(parse_graph(Some(input)).0), heur: heur_type.clone(), threads: *cnt, ..Flags::default() })))
+                    },
                     "hda" => {
-                        group.bench_function(&format, |b| b.iter(|| hda::setup(start, end,
-                            Flags { graph: parse_graph(Some(input)).0, heur: *heur_type, threads: *cnt })))
+                        group.bench_function(&format, |b| b.iter(|| hda::setup::<i128>(start, end,
+                            Flags { graph: Arc::new(parse_graph(Some(input)).0), heur: heur_type.clone(), threads: *cnt, ..Flags::default() })))
                     },
                     "dpa" => {
-                        group.bench_function(&format, |b| b.iter(|| dpa::setup(start, end,
-                            Flags { graph: parse_graph(Some(input)).0, heur: *heur_type, threads: *cnt })))
+                        group.bench_function(&format, |b| b.iter(|| dpa::setup::<i128>(start, end,
+                            Flags { graph: Arc::new(parse_graph(Some(input)).0), heur: heur_type.clone(), threads: *cnt, ..Flags::default() })))
                     },
                     "kpbfs" => {
-                        group.bench_function(&format, |b| b.iter(|| kpbfs::setup(start, end,
-                            Flags { graph: parse_graph(Some(input)).0, heur: *heur_type, threads: *cnt })))
+                        group.bench_function(&format, |b| b.iter(|| kpbfs::setup::<i128>(start, end,
+                            Flags { graph: Arc::new(parse_graph(Some(input)).0), heur: heur_type.clone(), threads: *cnt, ..Flags::default() })))
+                    },
+                    "wsbfs" => {
+                        group.bench_function(&format, |b| b.iter(|| wsbfs::setup::<i128>(start, end,
+                            Flags { graph: Arc::new(parse_graph(Some(input)).0), heur: heur_type.clone(), threads: *cnt, ..Flags::default() })))
+                    },
+                    "bidir" => {
+                        group.bench_function(&format, |b| b.iter(|| bidir::setup::<i128>(start, end,
+                            Flags { graph: Arc::new(parse_graph(Some(input)).0), heur: heur_type.clone(), threads: *cnt, ..Flags::default() })))
                     },
                     _ => { 
-                        group.bench_function(&format, |b| b.iter(|| hda::setup(start, end,
-                            Flags { graph: parse_graph(Some(input)).0, heur: *heur_type, threads: *cnt })))
+                        group.bench_function(&format, |b| b.iter(|| hda::setup::<i128>(start, end,
+                            Flags { graph: Arc::new(parse_graph(Some(input)).0), heur: heur_type.clone(), threads: *cnt, ..Flags::default() })))
                     },
                 };
 
@@ -87,6 +91,38 @@ fn criterion_benchmark(c: &mut Criterion) {
     };
 
     group.finish();
+
+    // HPA* is meant for many queries on one graph, so the index is built once
+    // outside the timed query. Compare hpa_query with hda_#t_euclidean above.
+    let mut group = c.benchmark_group("hpa");
+
+    group.sample_size(10);
+
+    let (graph, start, end) = parse_graph(Some(input));
+    let graph = Arc::new(graph);
+    let index = HpaIndex::new(graph.clone(), DEFAULT_CLUSTER_SIZE, 4);
+
+    group.bench_function("hpa_query", |b| b.iter(|| index.search::<i128>(start, end, &Euclidean, Limits::default())));
+
+    for cnt in thread_cnts.iter() {
+        group.bench_function(format!("hpa_build_{}t", *cnt), |b| b.iter(|| HpaIndex::new(graph.clone(), DEFAULT_CLUSTER_SIZE, *cnt)));
+    }
+
+    group.finish();
+
+    // A solver keeps its threads, the graph and hda's cell table between
+    // queries, where setup starts over and copies the graph for every query.
+    let mut group = c.benchmark_group("solver");
+
+    group.sample_size(10);
+
+    let solver = Solver::new(Algorithm::Hda, Flags { graph: graph.clone(), heur: Arc::new(Euclidean), threads: 4, ..Flags::default() });
+
+    group.bench_function("solver_hda_4t", |b| b.iter(|| solver.solve::<i128>(start, end, Limits::default())));
+    group.bench_function("setup_hda_4t", |b| b.iter(|| hda::setup::<i128>(start, end,
+        Flags { graph: Arc::new(graph.to_vec()), heur: Arc::new(Euclidean), threads: 4, ..Flags::default() })));
+
+    group.finish();
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -0,0 +1,2 @@
+extern crate atomic;
+pub mod a_star;
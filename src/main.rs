@@ -1,32 +1,152 @@
 #[macro_use]
 extern crate clap;
-extern crate atomic;
-mod a_star;
-use a_star::{
-    utils::structs::{HeurType, Flags},
+mod report;
+use std::{thread, thread::JoinHandle, sync::Arc, time::{Duration, Instant}};
+use clap::ArgMatches;
+use crossbeam::channel::unbounded;
+use parallel_astar_rust::a_star::{
+    utils::structs::{Flags, Point, Solution, SearchMode, TieBreak, Anytime, Improvement, Batching},
+    utils::cost::{Cost, Real},
+    utils::affinity::Pinning,
+    utils::limits::{Limits, StopReason},
+    utils::successors::{Successors, JumpTable},
+    utils::landmarks::{Landmarks, Selection},
+    utils::heuristics::{Registry, CostModel, Wait, Delay},
+    utils::stats::SearchStats,
+    astar,
     hda,
+    hpa,
     dpa,
     kpbfs,
+    wsbfs,
+    bidir,
+    ida,
+    theta,
     utils::helpers
 };
+use report::Run;
 
-fn validate_heuristic(heur: String) -> Result<(), String> {
-    match heur.as_str() {
-        "euclidean" => Ok(()),
-        "manhattan" => Ok(()),
-        "expensive" => Ok(()),
-        "nonadmissible" => Ok(()),
-        "expnon" => Ok(()),
-        _ => Err(String::from("Please input a valid heuristic option [euclidean, manhattan, expensive, nonadmissible, expnon]")),
+fn validate_selection(selection: String) -> Result<(), String> {
+    match selection.as_str() {
+        "farthest" => Ok(()),
+        "avoid" => Ok(()),
+        "random" => Ok(()),
+        _ => Err(String::from("Please input a valid landmark selection option [farthest, avoid, random]")),
+    }
+}
+
+fn validate_wait(wait: String) -> Result<(), String> {
+    match wait.as_str() {
+        "sleep" => Ok(()),
+        "spin" => Ok(()),
+        _ => Err(String::from("Please input a valid wait option [sleep, spin]")),
+    }
+}
+
+fn validate_distribution(distribution: String) -> Result<(), String> {
+    match distribution.as_str() {
+        "fixed" => Ok(()),
+        "uniform" => Ok(()),
+        "exponential" => Ok(()),
+        _ => Err(String::from("Please input a valid delay distribution option [fixed, uniform, exponential]")),
+    }
+}
+
+fn validate_output(output: String) -> Result<(), String> {
+    match output.as_str() {
+        "text" => Ok(()),
+        "json" => Ok(()),
+        "csv" => Ok(()),
+        _ => Err(String::from("Please input a valid output option [text, json, csv]")),
     }
 }
 
 fn validate_algo(algo: String) -> Result<(), String> {
     match algo.as_str() {
+        "astar" => Ok(()),
         "hda" => Ok(()),
+        "hpa" => Ok(()),
         "dpa" => Ok(()),
         "kpbfs" => Ok(()),
-        _ => Err(String::from("Please input a valid implementation option [hda, dpa, kpbfs]")),
+        "wsbfs" => Ok(()),
+        "bidir" => Ok(()),
+        "ida" => Ok(()),
+        "theta" => Ok(()),
+        "lazytheta" => Ok(()),
+        _ => Err(String::from("Please input a valid implementation option [astar, hda, hpa, dpa, kpbfs, wsbfs, bidir, ida, theta, lazytheta]")),
+    }
+}
+
+fn validate_cost(cost: String) -> Result<(), String> {
+    match cost.as_str() {
+        "i128" => Ok(()),
+        "u64" => Ok(()),
+        "u32" => Ok(()),
+        "real" => Ok(()),
+        _ => Err(String::from("Please input a valid cost option [i128, u64, u32, real]")),
+    }
+}
+
+fn validate_tie_break(tie_break: String) -> Result<(), String> {
+    match tie_break.as_str() {
+        "g" => Ok(()),
+        "h" => Ok(()),
+        "lifo" => Ok(()),
+        "fifo" => Ok(()),
+        "random" => Ok(()),
+        _ => Err(String::from("Please input a valid tie-break option [g, h, lifo, fifo, random]")),
+    }
+}
+
+fn validate_pinning(pinning: String) -> Result<(), String> {
+    match pinning.as_str() {
+        "round_robin" => Ok(()),
+        "compact" => Ok(()),
+        "scatter" => Ok(()),
+        _ => Err(String::from("Please input a valid pinning option [round_robin, compact, scatter]")),
+    }
+}
+
+fn validate_successors(successors: String) -> Result<(), String> {
+    match successors.as_str() {
+        "neighbors" => Ok(()),
+        "jps" => Ok(()),
+        "jps+" => Ok(()),
+        _ => Err(String::from("Please input a valid successor option [neighbors, jps, jps+]")),
+    }
+}
+
+fn validate_weight(weight: String) -> Result<(), String> {
+    match weight.parse::<f64>() {
+        Ok(w) if w >= 1.0 => Ok(()),
+        _ => Err(String::from("Please input a weight of at least 1.0")),
+    }
+}
+
+fn validate_count(count: String) -> Result<(), String> {
+    match count.parse::<u64>() {
+        Ok(_) => Ok(()),
+        _ => Err(String::from("Please input a whole number")),
+    }
+}
+
+fn validate_threads(threads: String) -> Result<(), String> {
+    match threads.parse::<usize>() {
+        Ok(n) if n >= 1 => Ok(()),
+        _ => Err(String::from("Please input at least 1 thread")),
+    }
+}
+
+/// Flags an algorithm has no use for, by argument name and as typed.
+fn unsupported(algo: &str) -> &'static [(&'static str, &'static str)] {
+    match algo {
+        // MM orders both frontiers by max(f, 2g), which has no weighted,
+        // tie-broken or anytime variant here.
+        "bidir" => &[("WEIGHT", "--weight"), ("focal", "--focal"), ("TIE_BREAK", "--tie_break"), ("anytime", "--anytime"),
+                     ("SUCCESSORS", "--successors")],
+        // These step to the four neighbours, or any visible cell, on their own.
+        "kpbfs" | "ida" | "hpa" | "theta" | "lazytheta" => &[("SUCCESSORS", "--successors")],
+        _ => &[],
     }
 }
 
@@ -35,32 +155,242 @@ fn main() {
     // and pass flags to kpbfs, dpa, and any other impls
     let config = clap_app!(a_star =>
         (@arg GRAPH: -g --graph +takes_value "Graph to use for algorithm implementation")
-        (@arg NUM_THREADS: -n --num_threads +takes_value "Number of threads to use")
-        (@arg ALGO: -a --algo +takes_value { validate_algo } "Underlying algorithm to use" )
-        (@arg HEURISTIC: -h --heur +takes_value { validate_heuristic } "Heuristic type to use")
+        (@arg NUM_THREADS: -n --num_threads +takes_value { validate_threads } "Number of threads to use")
+        (@arg ALGO: -a --algo +takes_value { validate_algo } "Underlying algorithm to use; hpa returns near-optimal rather than optimal paths" )
+        (@arg HEURISTIC: -h --heur +takes_value "Heuristic type to use")
+        (@arg WEIGHT: -w --weight +takes_value { validate_weight } "Suboptimality bound, returned cost is within this factor of optimal")
+        (@arg focal: --focal "Use focal search within the weight instead of inflating h")
+        (@arg TIE_BREAK: --tie_break +takes_value { validate_tie_break } "How open lists order nodes of equal f [g, h, lifo, fifo, random]")
+        (@arg anytime: --anytime "Keep improving on the first path found and print each new one")
+        (@arg DEADLINE: --deadline +takes_value { validate_count } "Stop the search after this many milliseconds")
+        (@arg MAX_EXPANSIONS: --max_expansions +takes_value { validate_count } "Stop the search after expanding this many nodes")
+        (@arg MAX_ENTRIES: --max_entries +takes_value { validate_count } "Stop the search once this many nodes have been stored")
+        (@arg TABLE_SIZE: --table_size +takes_value { validate_count } "Transposition table entries for ida, shared out between threads; one per cell for each thread by default")
+        (@arg SUCCESSORS: --successors +takes_value { validate_successors } "Successor generation for astar, hda, dpa and wsbfs")
+        (@arg BATCH_SIZE: --batch_size +takes_value { validate_count } "Nodes hda groups into one message, 1 sends each alone")
+        (@arg BATCH_WAIT: --batch_wait +takes_value { validate_count } "Microseconds hda holds back a batch that is not full")
+        (@arg COST: --cost +takes_value { validate_cost } "Type path costs are kept in [i128, u64, u32, real]")
+        (@arg PIN: --pin +takes_value { validate_pinning } "Pin hda, dpa and kpbfs workers to CPUs [round_robin, compact, scatter]")
+        (@arg smooth: --smooth "Cut the returned path down to the corners it has to turn at")
+        (@arg LANDMARKS: --landmarks +takes_value { validate_count } "Number of landmarks for the alt heuristic")
+        (@arg SELECTION: --landmark_selection +takes_value { validate_selection } "How alt landmarks are placed")
+        (@arg SEED: --seed +takes_value { validate_count } "Seed for non-admissible noise, heuristic delays and random landmarks")
+        (@arg WAIT: --heur_wait +takes_value { validate_wait } "Whether the expensive heuristics sleep or busy-spin")
+        (@arg DELAY: --heur_delay +takes_value { validate_count } "Mean delay of the expensive heuristics in microseconds")
+        (@arg DISTRIBUTION: --heur_delay_dist +takes_value { validate_distribution } "How the expensive heuristic delay varies [fixed, uniform, exponential]")
+        (@arg stats: --stats "Print what each thread expanded and generated, and for hda and dpa its messages")
+        (@arg OUTPUT: --output +takes_value { validate_output } "Print the run as a sentence, a line of JSON or a CSV row [text, json, csv]")
+        (@arg no_header: --no_header "Leave out the CSV header, for appending to an existing results file")
         (@arg debug: -d "Set debugging flag")
     ).get_matches();
 
     // Example cargo run -- --graph large2.in --num_threads 2 --algo hda --heur euclidean
 
-    let heur_type = match config.value_of("HUERISTIC").unwrap_or("euclidean") {
-        "euclidean" => HeurType::EuclideanDist,
-        "manhattan" => HeurType::ManhattanDist,
-        "expensive" => HeurType::Expensive,
-        "nonadmissible" => HeurType::NonAdmissible,
-        "expnon" => HeurType::ExpensiveNonAdmissible,
-        _ => HeurType::EuclideanDist,
-    };
+    let algo = config.value_of("ALGO").unwrap_or("hda");
+
+    if let Some((_, flag)) = unsupported(algo).iter().find(|(name, _)| config.is_present(name)) {
+        let message = format!("{} cannot be used with --algo {}", flag, algo);
+
+        clap::Error::with_description(&message, clap::ErrorKind::ArgumentConflict).exit()
+    }
 
     let threads = config.value_of("NUM_THREADS").unwrap_or("4").parse().unwrap_or(4);
     let (graph, start, end) = helpers::parse_graph(config.value_of("GRAPH"));
-    let flags = Flags { graph, heur: heur_type, threads: threads };
+
+    let selection = match config.value_of("SELECTION").unwrap_or("avoid") {
+        "farthest" => Selection::Farthest,
+        "random" => Selection::Random,
+        _ => Selection::Avoid,
+    };
+    let count = config.value_of("LANDMARKS").unwrap_or("8").parse().unwrap_or(8);
+    // Tables are saved next to the map and only rebuilt when the map or settings change.
+    let saved = format!("data/{}.dh", config.value_of("GRAPH").unwrap_or("medium1.in"));
+
+    let seed = config.value_of("SEED").unwrap_or("0").parse().unwrap_or(0);
+    let mean = Duration::from_micros(config.value_of("DELAY").unwrap_or("1000").parse().unwrap_or(1000));
+    let delay = match config.value_of("DISTRIBUTION").unwrap_or("fixed") {
+        "uniform" => Delay::Uniform(Duration::ZERO, mean * 2),
+        "exponential" => Delay::Exponential(mean),
+        _ => Delay::Fixed(mean),
+    };
+    let wait = if config.value_of("WAIT") == Some("spin") { Wait::Spin } else { Wait::Sleep };
+
+    let mut registry = Registry::built_in(seed, CostModel { wait, delay, seed });
+    registry.register("alt", move |graph| {
+        let landmarks = Landmarks::load(&saved, graph, count, selection, seed).unwrap_or_else(|_| {
+            let landmarks = Landmarks::new(graph, count, selection, seed, threads);
+
+            if let Err(error) = landmarks.save(&saved, graph) {
+                eprintln!("Could not save landmarks to {}: {}", saved, error);
+            }
+
+            landmarks
+        });
+
+        Arc::new(landmarks)
+    });
+
+    let heur_type = registry.build(config.value_of("HEURISTIC").unwrap_or("euclidean"), &graph).unwrap_or_else(|| {
+        let message = format!("Please input a valid heuristic option [{}]", registry.names().join(", "));
+
+        clap::Error::with_description(&message, clap::ErrorKind::InvalidValue).exit()
+    });
+
+    let output = config.value_of("OUTPUT").unwrap_or("text");
+    let weight = config.value_of("WEIGHT").unwrap_or("1.0").parse().unwrap_or(1.0);
+    let mode = if config.is_present("focal") { SearchMode::Focal(weight) } else { SearchMode::Weighted(weight) };
+    let pinning = match config.value_of("PIN") {
+        Some("round_robin") => Some(Pinning::RoundRobin),
+        Some("compact") => Some(Pinning::Compact),
+        Some("scatter") => Some(Pinning::Scatter),
+        _ => None,
+    };
+    let tie_break = match config.value_of("TIE_BREAK").unwrap_or("g") {
+        "h" => TieBreak::LowH,
+        "lifo" => TieBreak::Lifo,
+        "fifo" => TieBreak::Fifo,
+        "random" => TieBreak::Random(seed),
+        _ => TieBreak::HighG,
+    };
+
+    // Anytime improvements are printed as they arrive, the channel closes once the search returns.
+    // They go to stderr when stdout is kept for the run record.
+    let mut reporter = None;
+    let anytime = if config.is_present("anytime") {
+        let text = output == "text";
+        let (updates, improvements) = unbounded::<Improvement>();
+
+        reporter = Some(thread::spawn(move || {
+            for Improvement { cost, path, elapsed } in improvements {
+                let line = format!("[{:?}] Found path of cost {} ({} steps)", elapsed, cost, path.len().saturating_sub(1));
+
+                if text { println!("{}", line) } else { eprintln!("{}", line) }
+            }
+        }));
+
+        Some(Anytime::new(updates))
+    }
+    else {
+        None
+    };
+
+    let limits = Limits {
+        deadline: config.value_of("DEADLINE").map(|ms| Instant::now() + Duration::from_millis(ms.parse().unwrap())),
+        max_expansions: config.value_of("MAX_EXPANSIONS").map(|n| n.parse().unwrap()),
+        max_entries: config.value_of("MAX_ENTRIES").map(|n| n.parse().unwrap()),
+        ..Limits::default()
+    };
+
+    let successors = match config.value_of("SUCCESSORS").unwrap_or("neighbors") {
+        "jps" => Successors::Jps,
+        "jps+" => Successors::JpsPlus(Arc::new(JumpTable::new(&graph))),
+        _ => Successors::Neighbors,
+    };
+
+    // Without a table ida re-searches the same cells down every path to them,
+    // and with much less than a slot per cell it keeps evicting them.
+    let table_size = config.value_of("TABLE_SIZE").map_or(graph.len() * graph.len() * threads, |n| n.parse().unwrap());
+    let batching = Batching {
+        size: config.value_of("BATCH_SIZE").map_or(Batching::default().size, |n| n.parse::<usize>().unwrap().max(1)),
+        wait: config.value_of("BATCH_WAIT").map_or(Batching::default().wait, |us| Duration::from_micros(us.parse().unwrap())),
+    };
+    let flags = Flags { graph: Arc::new(graph), heur: heur_type, threads, mode, anytime, limits, table_size: Some(table_size), successors, batching, tie_break, pinning, pool: None };
+
+    match config.value_of("COST").unwrap_or("i128") {
+        "u32" => solve::<u32>(&config, start, end, flags, reporter),
+        "u64" => solve::<u64>(&config, start, end, flags, reporter),
+        "real" => solve::<Real>(&config, start, end, flags, reporter),
+        _ => solve::<i128>(&config, start, end, flags, reporter),
+    }
+}
+
+/// Runs the chosen algorithm with costs of type `C` and prints the result.
+fn solve<C: Cost>(config: &ArgMatches, start: Point, end: Point, flags: Flags, reporter: Option<JoinHandle<()>>) {
+    let threads = flags.threads;
+    let smooth_graph = if config.is_present("smooth") { Some(flags.graph.clone()) } else { None };
+    let output = config.value_of("OUTPUT").unwrap_or("text");
     let algo = config.value_of("ALGO").unwrap_or("hda");
+    let started = Instant::now();
 
-    match algo {
+    let mut solution: Solution<C> = match algo {
+        "astar" => astar::setup(start, end, flags),
         "hda" => hda::setup(start, end, flags),
+        "hpa" => hpa::setup(start, end, flags),
         "dpa" => dpa::setup(start, end, flags),
         "kpbfs" => kpbfs::setup(start, end, flags),
+        "wsbfs" => wsbfs::setup(start, end, flags),
+        "bidir" => bidir::setup(start, end, flags),
+        "ida" => ida::setup(start, end, flags),
+        "theta" => theta::setup(start, end, flags),
+        "lazytheta" => theta::setup_lazy(start, end, flags),
         _ => hda::setup(start, end, flags),
+    };
+
+    let elapsed = started.elapsed();
+
+    if let Some(reporter) = reporter {
+        reporter.join().expect("Panic");
+    }
+
+    if let Some(graph) = smooth_graph {
+        solution.path = helpers::smooth_path(&graph, &solution.path);
+    }
+
+    if output != "text" {
+        let run = Run {
+            algo,
+            map: config.value_of("GRAPH").unwrap_or("medium1.in"),
+            threads,
+            heuristic: config.value_of("HEURISTIC").unwrap_or("euclidean"),
+            elapsed,
+            solution: &solution,
+        };
+
+        match output {
+            "json" => println!("{}", run.json()),
+            _ => {
+                if !config.is_present("no_header") {
+                    println!("{}", Run::<C>::csv_header());
+                }
+
+                println!("{}", run.csv());
+            },
+        }
+
+        return;
+    }
+
+    if solution.reason != StopReason::Completed {
+        println!("Search stopped early: {}", solution.reason);
     }
-}
\ No newline at end of file
+
+    match solution.cost {
+        Some(cost) => println!("All threads found goal node {},{}. Cost of {}", end.x, end.y, cost),
+        None => println!("No path to goal node {},{}", end.x, end.y),
+    }
+
+    if solution.cost.is_some() && (config.is_present("smooth") || algo.ends_with("theta")) {
+        println!("Any-angle length of {:.3} through {} waypoints", helpers::path_length(&solution.path), solution.path.len());
+    }
+
+    if config.is_present("stats") && !solution.stats.is_empty() {
+        for (thread, stats) in solution.stats.iter().enumerate() {
+            println!("Thread {}: {}", thread, stats);
+        }
+
+        println!("Total: {}", solution.stats.iter().sum::<SearchStats>());
+
+        for comm in solution.comm.iter() {
+            println!("Thread {} messages: {}", comm.thread, comm);
+        }
+    }
+
+    if config.is_present("PIN") {
+        for (thread, cpu) in solution.placement.iter().enumerate() {
+            match cpu {
+                Some(cpu) => println!("Thread {} pinned to CPU {}", thread, cpu),
+                None => println!("Thread {} could not be pinned", thread),
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::time::Duration;
+use parallel_astar_rust::a_star::utils::{
+    structs::Solution,
+    cost::Cost,
+    stats::{SearchStats, CommStats},
+    helpers
+};
+
+/// Columns of a run, in the order both formats write them. New columns go
+/// on the end so existing results files keep lining up.
+pub const COLUMNS: [&str; 19] = [
+    "algo", "map", "threads", "heuristic", "reason", "cost", "path_length", "waypoints", "elapsed_ms",
+    "expansions", "generated", "duplicates", "reopened", "evaluations", "messages", "stolen", "cpus",
+    "table_entries", "steals",
+];
+
+/// One finished run of the binary, for `--output json` and `--output csv`.
+pub struct Run<'a, C: Cost> {
+    pub algo: &'a str,
+    pub map: &'a str,
+    pub threads: usize,
+    pub heuristic: &'a str,
+    pub elapsed: Duration,
+    pub solution: &'a Solution<C>,
+}
+
+/// A field before it is written out in either format.
+enum Value {
+    Text(String),
+    Number(String),
+    Missing,
+}
+
+impl<'a, C: Cost> Run<'a, C> {
+    fn values(&self) -> Vec<Value> {
+        let solution = self.solution;
+        let found = solution.cost.is_some();
+        let totals = solution.stats.iter().sum::<SearchStats>();
+        let stat = |value: u64| if solution.stats.is_empty() { Value::Missing } else { Value::Number(value.to_string()) };
+
+        vec![
+            Value::Text(self.algo.to_string()),
+            Value::Text(self.map.to_string()),
+            Value::Number(self.threads.to_string()),
+            Value::Text(self.heuristic.to_string()),
+            Value::Text(solution.reason.to_string()),
+            solution.cost.map_or(Value::Missing, |cost| Value::Number(cost.to_string())),
+            if found { Value::Number(format!("{:.3}", helpers::path_length(&solution.path))) } else { Value::Missing },
+            if found { Value::Number(solution.path.len().to_string()) } else { Value::Missing },
+            Value::Number(format!("{:.3}", self.elapsed.as_secs_f64() * 1000.0)),
+            stat(totals.expansions),
+            stat(totals.generated),
+            stat(totals.duplicates),
+            stat(totals.reopened),
+            stat(totals.evaluations),
+            if solution.comm.is_empty() { Value::Missing }
+            else { Value::Number(solution.comm.iter().map(|comm| comm.batches).sum::<u64>().to_string()) },
+            stat(totals.stolen),
+            if solution.placement.iter().all(Option::is_none) { Value::Missing }
+            else { Value::Text(placement(&solution.placement)) },
+            stat(totals.table_entries),
+            stat(totals.steals),
+        ]
+    }
+
+    /// One JSON object on a single line, so a results file holds one run
+    /// per line. Alongside the columns, `per_thread` has each thread's
+    /// statistics and, for hda and dpa, its messages.
+    pub fn json(&self) -> String {
+        let fields: Vec<String> = COLUMNS.iter().zip(self.values())
+            .map(|(name, value)| format!("{}:{}", json_string(name), json_value(&value)))
+            .collect();
+        let threads: Vec<String> = self.solution.stats.iter().enumerate()
+            .map(|(thread, stats)| thread_json(stats, self.solution.comm.get(thread), self.solution.placement.get(thread)))
+            .collect();
+
+        format!("{{{},\"per_thread\":[{}]}}", fields.join(","), threads.join(","))
+    }
+
+    pub fn csv_header() -> String {
+        COLUMNS.join(",")
+    }
+
+    pub fn csv(&self) -> String {
+        self.values().iter()
+            .map(|value| match value {
+                Value::Text(text) => csv_field(text),
+                Value::Number(number) => number.clone(),
+                Value::Missing => String::new(),
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+fn thread_json(stats: &SearchStats, comm: Option<&CommStats>, cpu: Option<&Option<usize>>) -> String {
+    let mut fields = vec![
+        format!("\"expansions\":{}", stats.expansions),
+        format!("\"generated\":{}", stats.generated),
+        format!("\"duplicates\":{}", stats.duplicates),
+        format!("\"reopened\":{}", stats.reopened),
+        format!("\"evaluations\":{}", stats.evaluations),
+        format!("\"stolen\":{}", stats.stolen),
+        format!("\"steals\":{}", stats.steals),
+        format!("\"table_entries\":{}", stats.table_entries),
+    ];
+
+    if let Some(cpu) = cpu {
+        fields.push(format!("\"cpu\":{}", cpu.map_or(String::from("null"), |cpu| cpu.to_string())));
+    }
+
+    if let Some(comm) = comm {
+        let sent: Vec<String> = comm.sent.iter().map(u64::to_string).collect();
+
+        fields.push(format!("\"sent\":[{}]", sent.join(",")));
+        fields.push(format!("\"messages\":{}", comm.batches));
+        fields.push(format!("\"received\":{}", comm.received));
+        fields.push(format!("\"queue_high_water\":{}", comm.queue_high_water));
+        fields.push(format!("\"blocked_ms\":{:.3}", comm.blocked.as_secs_f64() * 1000.0));
+        fields.push(format!("\"idle_iterations\":{}", comm.idle_iterations));
+    }
+
+    format!("{{{}}}", fields.join(","))
+}
+
+/// Each worker's CPU in thread order, "-" for one left unpinned.
+fn placement(cpus: &[Option<usize>]) -> String {
+    cpus.iter().map(|cpu| cpu.map_or(String::from("-"), |cpu| cpu.to_string())).collect::<Vec<_>>().join(" ")
+}
+
+fn json_value(value: &Value) -> String {
+    match value {
+        Value::Text(text) => json_string(text),
+        Value::Number(number) => number.clone(),
+        Value::Missing => String::from("null"),
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+/// Quotes a field holding a comma, quote or line break, doubling its quotes.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    }
+    else {
+        text.to_string()
+    }
+}
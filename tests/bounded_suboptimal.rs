@@ -0,0 +1,81 @@
+use std::sync::Arc;
+use parallel_astar_rust::a_star::{
+    utils::structs::{Flags, Point, Solution, SearchMode},
+    utils::heuristics::Euclidean,
+    utils::helpers,
+    hda,
+    dpa
+};
+
+const QUERIES: usize = 30;
+const THREADS: usize = 3;
+const WEIGHT: f64 = 1.5;
+
+/// Linear congruential generator, so the queries are the same every run.
+struct Lcg(u64);
+
+impl Lcg {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 = self.0.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        ((self.0 >> 33) % n as u64) as usize
+    }
+}
+
+/// Random pairs of open cells on small1. Pairs that cannot reach each other
+/// are fine: the search then returns no path, which is checked as well.
+fn queries(graph: &[Vec<char>]) -> Vec<(Point, Point)> {
+    let mut random = Lcg(QUERIES as u64);
+    let mut queries = Vec::with_capacity(QUERIES);
+
+    while queries.len() < QUERIES {
+        let mut cell = || Point { x: random.below(graph.len()) as i32, y: random.below(graph.len()) as i32 };
+        let (start, end) = (cell(), cell());
+
+        if graph[start.x as usize][start.y as usize] != 'W' && graph[end.x as usize][end.y as usize] != 'W' && start != end {
+            queries.push((start, end));
+        }
+    }
+
+    queries
+}
+
+/// The returned path must cost what the search says, from start to goal
+/// one step at a time.
+fn path_matches_cost(algo: &str, setup: fn(Point, Point, Flags) -> Solution<i128>) {
+    let (graph, _, _) = helpers::parse_graph(Some("small1.in"));
+    let graph = Arc::new(graph);
+
+    for mode in [SearchMode::Weighted(WEIGHT), SearchMode::Focal(WEIGHT)].iter() {
+        for (start, end) in queries(&graph) {
+            let flags = Flags { graph: graph.clone(), heur: Arc::new(Euclidean), threads: THREADS, mode: *mode, ..Flags::default() };
+            let solution = setup(start, end, flags);
+            let case = format!("{} from {:?} to {:?} with {:?}", algo, start, end, mode);
+
+            let cost = match solution.cost {
+                Some(cost) => cost,
+                None => {
+                    assert!(solution.path.is_empty(), "path without a cost on {}", case);
+                    continue;
+                },
+            };
+
+            assert_eq!(solution.path.len() as i128 - 1, cost, "path length on {}", case);
+            assert_eq!(solution.path.first(), Some(&start), "path start on {}", case);
+            assert_eq!(solution.path.last(), Some(&end), "path end on {}", case);
+
+            for pair in solution.path.windows(2) {
+                assert_eq!((pair[1].x - pair[0].x).abs() + (pair[1].y - pair[0].y).abs(), 1, "gap in path on {}", case);
+            }
+        }
+    }
+}
+
+#[test]
+fn hda_path_matches_cost() {
+    path_matches_cost("hda", hda::setup);
+}
+
+#[test]
+fn dpa_path_matches_cost() {
+    path_matches_cost("dpa", dpa::setup);
+}
@@ -0,0 +1,24 @@
+use std::sync::Arc;
+use parallel_astar_rust::a_star::utils::{
+    structs::Point,
+    cell_table::{Cell, CellTable, GridTable}
+};
+
+#[test]
+fn grid_keeps_g_too_large_for_an_entry() {
+    let graph = vec![vec!['.'; 4]; 4];
+    let grid = Arc::new(GridTable::for_graph::<u64>(&graph).unwrap());
+    let mut cells: CellTable<u64> = CellTable::new(Some(grid));
+    let (near, far) = (Point { x: 1, y: 2 }, Point { x: 3, y: 0 });
+    let large = u32::MAX as u64 + 5;
+
+    cells.set(near, Cell { g: 7, closed: false });
+    cells.set(far, Cell { g: large, closed: false });
+
+    assert_eq!(cells.get(near), Some(Cell { g: 7, closed: false }));
+    assert_eq!(cells.get(far), Some(Cell { g: large, closed: false }));
+
+    // Back under the limit, the entry itself is used again.
+    cells.set(far, Cell { g: 12, closed: true });
+    assert_eq!(cells.get(far), Some(Cell { g: 12, closed: true }));
+}
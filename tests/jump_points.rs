@@ -0,0 +1,196 @@
+use std::{collections::VecDeque, sync::Arc};
+use parallel_astar_rust::a_star::{
+    utils::structs::{Flags, Point, Solution},
+    utils::successors::{Successors, JumpTable},
+    utils::heuristics::Euclidean,
+    utils::helpers,
+    astar,
+    hda,
+    dpa
+};
+
+// Extra start and goal pairs drawn on each map, on top of its own.
+const PAIRS: usize = 6;
+
+/// A map's name, its cells, and its own start and goal if it has them.
+type Map = (String, Vec<Vec<char>>, Option<(Point, Point)>);
+
+/// Linear congruential generator, so the maps and pairs are the same every run.
+struct Lcg(u64);
+
+impl Lcg {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 = self.0.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        ((self.0 >> 33) % n as u64) as usize
+    }
+}
+
+fn open_map(size: usize) -> Vec<Vec<char>> {
+    vec![vec!['.'; size]; size]
+}
+
+/// Depth-first maze on the odd cells, with some walls knocked out so there
+/// are loops and more than one way through.
+fn maze_map(cells: usize, seed: u64) -> Vec<Vec<char>> {
+    let size = 2 * cells + 1;
+    let mut graph = vec![vec!['W'; size]; size];
+    let mut random = Lcg(seed);
+    let mut stack = vec![(1, 1)];
+
+    graph[1][1] = '.';
+
+    while let Some(&(x, y)) = stack.last() {
+        let unvisited: Vec<(usize, usize)> = [(0, 2), (2, 0), (0, -2), (-2, 0)].iter()
+            .map(|(dx, dy)| ((x as i32 + dx) as usize, (y as i32 + dy) as usize))
+            .filter(|(nx, ny)| *nx > 0 && *ny > 0 && *nx < size - 1 && *ny < size - 1 && graph[*nx][*ny] == 'W')
+            .collect();
+
+        if unvisited.is_empty() {
+            stack.pop();
+            continue;
+        }
+
+        let (nx, ny) = unvisited[random.below(unvisited.len())];
+
+        graph[(x + nx) / 2][(y + ny) / 2] = '.';
+        graph[nx][ny] = '.';
+        stack.push((nx, ny));
+    }
+
+    for _ in 0..cells * cells / 8 {
+        let (x, y) = (1 + random.below(size - 2), 1 + random.below(size - 2));
+
+        graph[x][y] = '.';
+    }
+
+    graph
+}
+
+fn walkable(graph: &[Vec<char>], point: Point) -> bool {
+    point.x >= 0 && point.y >= 0 && (point.x as usize) < graph.len() && (point.y as usize) < graph.len()
+        && graph[point.x as usize][point.y as usize] != 'W'
+}
+
+/// Steps on the shortest path, by breadth-first search.
+fn bfs(graph: &[Vec<char>], start: Point, end: Point) -> Option<i128> {
+    let mut distance = vec![vec![None; graph.len()]; graph.len()];
+    let mut queue = VecDeque::from(vec![start]);
+
+    distance[start.x as usize][start.y as usize] = Some(0);
+
+    while let Some(point) = queue.pop_front() {
+        let steps = distance[point.x as usize][point.y as usize].unwrap();
+
+        if point == end {
+            return Some(steps);
+        }
+
+        for (x, y) in [(0, 1), (-1, 0), (1, 0), (0, -1)].iter() {
+            let next = Point { x: point.x + x, y: point.y + y };
+
+            if walkable(graph, next) && distance[next.x as usize][next.y as usize].is_none() {
+                distance[next.x as usize][next.y as usize] = Some(steps + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+/// The map's own start and goal, then random pairs of open cells that can
+/// reach each other.
+fn pairs(graph: &[Vec<char>], own: Option<(Point, Point)>, seed: u64) -> Vec<(Point, Point, i128)> {
+    let mut random = Lcg(seed);
+    let mut pairs: Vec<(Point, Point, i128)> = own.into_iter()
+        .filter_map(|(start, end)| bfs(graph, start, end).map(|steps| (start, end, steps)))
+        .collect();
+
+    while pairs.len() < PAIRS + own.iter().count() {
+        let mut cell = || Point { x: random.below(graph.len()) as i32, y: random.below(graph.len()) as i32 };
+        let (start, end) = (cell(), cell());
+
+        if !walkable(graph, start) || !walkable(graph, end) || start == end {
+            continue;
+        }
+
+        if let Some(steps) = bfs(graph, start, end) {
+            pairs.push((start, end, steps));
+        }
+    }
+
+    pairs
+}
+
+fn strategies(graph: &[Vec<char>]) -> Vec<(&'static str, Successors)> {
+    vec![
+        ("neighbors", Successors::Neighbors),
+        ("jps", Successors::Jps),
+        ("jps+", Successors::JpsPlus(Arc::new(JumpTable::new(graph)))),
+    ]
+}
+
+/// Runs `setup` with every successor strategy between every pair and checks
+/// it finds a shortest path that walks cell by cell from start to goal.
+fn matches_bfs(map: &str, graph: Vec<Vec<char>>, own: Option<(Point, Point)>, setup: fn(Point, Point, Flags) -> Solution<i128>, threads: usize) {
+    let graph = Arc::new(graph);
+
+    for (start, end, steps) in pairs(&graph, own, graph.len() as u64) {
+        for (name, successors) in strategies(&graph) {
+            let flags = Flags { graph: graph.clone(), heur: Arc::new(Euclidean), threads, successors, ..Flags::default() };
+            let solution = setup(start, end, flags);
+            let case = format!("{} from {:?} to {:?} with {} on {} threads", map, start, end, name, threads);
+
+            assert_eq!(solution.cost, Some(steps), "cost on {}", case);
+            assert_eq!(solution.path.first(), Some(&start), "path start on {}", case);
+            assert_eq!(solution.path.last(), Some(&end), "path end on {}", case);
+            assert_eq!(solution.path.len() as i128, steps + 1, "path length on {}", case);
+
+            for pair in solution.path.windows(2) {
+                assert_eq!((pair[1].x - pair[0].x).abs() + (pair[1].y - pair[0].y).abs(), 1, "gap in path on {}", case);
+                assert!(walkable(&graph, pair[1]), "path through a wall on {}", case);
+            }
+        }
+    }
+}
+
+fn maps() -> Vec<Map> {
+    let mut maps = vec![
+        (String::from("open"), open_map(48), None),
+        (String::from("maze"), maze_map(20, 7), None),
+        (String::from("maze_small"), maze_map(12, 11), None),
+    ];
+
+    for file in ["trap1.in", "trap2.in", "small1.in"].iter() {
+        let (graph, start, end) = helpers::parse_graph(Some(file));
+
+        maps.push((file.to_string(), graph, Some((start, end))));
+    }
+
+    maps
+}
+
+#[test]
+fn astar_successors_match_bfs() {
+    for (map, graph, own) in maps() {
+        matches_bfs(&map, graph, own, astar::setup, 1);
+    }
+}
+
+#[test]
+fn hda_successors_match_bfs() {
+    for (map, graph, own) in maps() {
+        for threads in [1, 4].iter() {
+            matches_bfs(&map, graph.clone(), own, hda::setup, *threads);
+        }
+    }
+}
+
+#[test]
+fn dpa_successors_match_bfs() {
+    for (map, graph, own) in maps() {
+        for threads in [1, 4].iter() {
+            matches_bfs(&map, graph.clone(), own, dpa::setup, *threads);
+        }
+    }
+}
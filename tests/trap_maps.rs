@@ -0,0 +1,48 @@
+use std::{fs, sync::Arc};
+use parallel_astar_rust::a_star::{
+    utils::structs::{Flags, Point, Solution},
+    utils::heuristics::Registry,
+    utils::helpers,
+    hda,
+    dpa
+};
+
+// On these maps the first path to reach the goal is not the cheapest.
+const MAPS: [&str; 2] = ["trap1", "trap2"];
+const HEURISTICS: [&str; 2] = ["euclidean", "nonadmissible"];
+const THREADS: [usize; 3] = [1, 2, 4];
+
+/// Optimal cost recorded next to the map.
+fn expected(map: &str) -> i128 {
+    fs::read_to_string(format!("data/{}.out", map)).expect("Could not open file").trim().parse().unwrap()
+}
+
+fn finds_optimal(algo: &str, setup: fn(Point, Point, Flags) -> Solution<i128>) {
+    for map in MAPS.iter() {
+        let (graph, start, end) = helpers::parse_graph(Some(&format!("{}.in", map)));
+        let graph = Arc::new(graph);
+
+        for name in HEURISTICS.iter() {
+            let heur = Registry::default().build(name, &graph).unwrap();
+
+            for threads in THREADS.iter() {
+                let flags = Flags { graph: graph.clone(), heur: heur.clone(), threads: *threads, ..Flags::default() };
+                let solution = setup(start, end, flags);
+                let cost = expected(map);
+
+                assert_eq!(solution.cost, Some(cost), "{} on {} with {} and {} threads", algo, map, name, threads);
+                assert_eq!(solution.path.len() as i128, cost + 1, "{} path on {} with {} and {} threads", algo, map, name, threads);
+            }
+        }
+    }
+}
+
+#[test]
+fn hda_finds_optimal_paths() {
+    finds_optimal("hda", hda::setup);
+}
+
+#[test]
+fn dpa_finds_optimal_paths() {
+    finds_optimal("dpa", dpa::setup);
+}